@@ -1,217 +1,5211 @@
+pub mod parser;
+
 use parking_lot::Mutex;
+use parser::{
+    classify_failure, classify_icmp_error, is_duplicate_line, parse_latency_anomaly,
+    parse_packet_loss, parse_ping_from, parse_ping_header, parse_ping_reply, parse_ping_seq,
+    parse_rtt_summary, parse_traceroute_hop, FailureKind, IcmpErrorKind, ParsedReply, RttSummary,
+};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::io::{BufRead, BufReader};
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use std::process::{Child, Command, Stdio};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::task::{ready, Context, Poll};
 use std::thread;
-use tauri::{AppHandle, Emitter, State};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use surge_ping::{Client, Config, PingIdentifier, PingSequence, ICMP};
+use tauri::{AppHandle, Emitter, Manager, RunEvent, Runtime, State};
+use tauri_plugin_store::StoreExt;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+// A server's label and color as chosen in the UI
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ServerMeta {
+    label: Option<String>,
+    color: Option<String>,
+}
 
 // State to track running ping processes
 pub struct PingManager {
     processes: Arc<Mutex<HashMap<String, PingProcess>>>,
+    // Per-sample records of each server's latest run, kept after it finishes
+    samples: Arc<Mutex<HashMap<String, VecDeque<Sample>>>>,
+    // How each server's latest run was started, kept alongside its samples
+    sessions: Arc<Mutex<HashMap<String, Arc<Session>>>>,
+    // What each server's address last resolved to, to spot DNS changes between runs
+    resolved_ips: Arc<Mutex<HashMap<String, IpAddr>>>,
+    // Each server's last successful resolution, pinged instead when DNS
+    // fails; forgotten once the server is stopped
+    last_good_ips: Arc<Mutex<HashMap<String, IpAddr>>>,
+    // Upper bound on simultaneously running pings
+    max_concurrent: usize,
+    // Detected implementation of each ping binary used so far
+    flavors: Mutex<HashMap<PathBuf, PingFlavor>>,
+    // Starts the ping binary; replaced in tests to feed canned output
+    spawner: Arc<dyn ProcessSpawner>,
+    // Presentation details echoed in each server's events, so every window
+    // renders it the same way
+    server_meta: Mutex<HashMap<String, ServerMeta>>,
+}
+
+// Starts a built ping command and hands back its output
+pub trait ProcessSpawner: Send + Sync {
+    fn spawn(&self, cmd: Command) -> io::Result<Spawned>;
+
+    // Whether a program a command runs exists, asked before spawning ping
+    // since a wrapper around it would otherwise hide that it's missing
+    fn can_run(&self, program: &Path) -> bool {
+        !ping_missing(program)
+    }
+}
+
+// A started ping: its output, and the child to stop when there is a real one
+pub struct Spawned {
+    pub child: Option<Child>,
+    pub stdout: OutputReader,
+    pub stderr: Option<OutputReader>,
+}
+
+// One of ping's output pipes. They're read from async tasks, so a server
+// list of hundreds doesn't keep a thread waiting on each
+pub type OutputReader = Box<dyn AsyncBufRead + Send + Unpin>;
+
+// Runs the command as a subprocess
+struct CommandSpawner;
+
+impl ProcessSpawner for CommandSpawner {
+    fn spawn(&self, mut cmd: Command) -> io::Result<Spawned> {
+        let mut child = cmd.spawn()?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| io::Error::other("Failed to get stdout"))?;
+
+        // The pipes register with the reactor of the runtime reading them:
+        // the caller's when it has one, like `ping_stream`'s, else Tauri's
+        let tauri_runtime = tokio::runtime::Handle::try_current()
+            .is_err()
+            .then(tauri::async_runtime::handle);
+        let _context = tauri_runtime.as_ref().map(|runtime| runtime.inner().enter());
+        let stdout = tokio::process::ChildStdout::from_std(stdout)?;
+        let stderr: Option<OutputReader> = match child.stderr.take() {
+            Some(stderr) => Some(Box::new(BufReader::new(
+                tokio::process::ChildStderr::from_std(stderr)?,
+            ))),
+            None => None,
+        };
+        Ok(Spawned {
+            child: Some(child),
+            stdout: Box::new(BufReader::new(stdout)),
+            stderr,
+        })
+    }
 }
 
 struct PingProcess {
-    child: Child,
-    stop_flag: Arc<Mutex<bool>>,
+    // `None` for native ICMP and TCP pings, which run without a subprocess
+    child: Option<Child>,
+    stop_flag: Arc<AtomicBool>,
+    run: Arc<RunState>,
+    started_at: Instant,
 }
 
-impl Default for PingManager {
+// State a running ping's reader shares with the commands inspecting it,
+// locked separately from the processes map so reads don't stall the reader
+struct RunState {
+    recent: Mutex<VecDeque<PingResult>>,
+    // Paused runs stay alive but neither record nor emit anything
+    paused: Mutex<bool>,
+    // Cleared while the frontend's window is hidden: the run still records
+    // everything but only emits `ping-complete`
+    emit_enabled: AtomicBool,
+    // Echoes after which the run completes, set while it is running
+    target_count: Mutex<Option<u32>>,
+    last: Mutex<LastResult>,
+    // Successful latencies, summarized as they come in
+    latency: Mutex<LatencyStats>,
+}
+
+// Last-known values of a run, for gauges that are scraped instead of streamed
+#[derive(Clone, Default)]
+struct LastResult {
+    // Latency of the most recent reply
+    time_ms: Option<f64>,
+    // Whether the most recent echo was answered; `None` before the first
+    up: Option<bool>,
+    replies: u32,
+    failures: u32,
+}
+
+impl LastResult {
+    // Share of echoes answered so far, `None` until the first one is counted
+    fn availability(&self) -> Option<f64> {
+        let echoes = self.replies + self.failures;
+        (echoes > 0).then(|| f64::from(self.replies) / f64::from(echoes))
+    }
+}
+
+impl Default for RunState {
     fn default() -> Self {
         Self {
-            processes: Arc::new(Mutex::new(HashMap::new())),
+            recent: Mutex::default(),
+            paused: Mutex::default(),
+            emit_enabled: AtomicBool::new(true),
+            target_count: Mutex::default(),
+            last: Mutex::default(),
+            latency: Mutex::default(),
         }
     }
 }
 
-#[derive(Clone, Serialize)]
-struct PingResult {
-    server_id: String,
-    time_ms: f64,
-}
+impl RunState {
+    // A restarted run picks up where the previous one left off, still paused
+    // if it was; a target count set on it counts from the restart, like the
+    // run's own count
+    fn carried_over(previous: &RunState) -> Self {
+        Self {
+            recent: Mutex::new(previous.recent.lock().clone()),
+            paused: Mutex::new(*previous.paused.lock()),
+            emit_enabled: AtomicBool::new(previous.emits()),
+            target_count: Mutex::new(*previous.target_count.lock()),
+            last: Mutex::new(previous.last.lock().clone()),
+            latency: Mutex::new(previous.latency.lock().clone()),
+        }
+    }
 
-#[derive(Clone, Serialize)]
-struct PingTimeout {
-    server_id: String,
+    fn emits(&self) -> bool {
+        self.emit_enabled.load(Ordering::Relaxed)
+    }
+
+    fn push_recent(&self, result: PingResult) {
+        let mut recent = self.recent.lock();
+        if recent.len() >= MAX_RECENT_RESULTS {
+            recent.pop_front();
+        }
+        recent.push_back(result);
+    }
 }
 
-#[derive(Clone, Serialize)]
-struct PingEvent {
+// What a running ping's reader needs to report results and clean up after itself
+struct RunContext<R: Runtime> {
+    app: AppHandle<R>,
     server_id: String,
+    processes: Arc<Mutex<HashMap<String, PingProcess>>>,
+    samples: Arc<Mutex<HashMap<String, VecDeque<Sample>>>>,
+    resolved_ips: Arc<Mutex<HashMap<String, IpAddr>>>,
+    last_good_ips: Arc<Mutex<HashMap<String, IpAddr>>>,
+    spawner: Arc<dyn ProcessSpawner>,
+    stop_flag: Arc<AtomicBool>,
+    run: Arc<RunState>,
+    // The ping binary's own summary line, if it printed one
+    rtt_summary: Option<RttSummary>,
+    alert_threshold_ms: Option<f64>,
+    // Whether the last reply was above the threshold, so alerts only fire on crossings
+    alerting: bool,
+    session: Option<Arc<Session>>,
+    flavor: PingFlavor,
+    // Replies are sent as one `ping-batch` per this many instead of one by one
+    batch_size: Option<u32>,
+    batch: Vec<PingResult>,
+    // Replies arriving sooner than this after the last emitted one are
+    // counted but not sent
+    min_emit_interval: Option<Duration>,
+    last_emit_at: Option<Instant>,
+    notify_on_failure: bool,
+    // Send `ping-status-change` on flips between up and down instead of
+    // per-echo events
+    transitions_only: bool,
+    // Failures since the last reply, for deciding when to send `ping-notify`
+    failure_streak: u32,
+    // For counting replies that arrive after a later one; Windows replies
+    // carry no sequence number and are numbered in arrival order, so only
+    // Unix pings can ever count any
+    highest_seq: Option<u32>,
+    reorder_count: u32,
+    // Echoes iputils gave up waiting on, most recent last, so a reply that
+    // still turns up isn't counted as another echo
+    lost_seqs: VecDeque<u32>,
+    // Echoes answered or lost so far out of the run's count, for `ping-progress`
+    echoes_done: u32,
+    echoes_total: Option<u32>,
+    last_progress_at: Option<Instant>,
+    // Reference point for the replies' `offset_ns`
+    started_at: Instant,
+    // The address replies should come from, when it's known
+    expected_ip: Option<IpAddr>,
+    // The last unexpected sender reported, so each is only reported once in a row
+    unexpected_source: Option<IpAddr>,
+    // Ping closed its output, so it has exited or is about to and can be waited on
+    output_ended: bool,
+    ema_alpha: Option<f64>,
+    // Smoothed latency, seeded by the first reply
+    ema_ms: Option<f64>,
+    // When `deadline_secs` runs out
+    deadline: Option<Instant>,
+    health: Option<HealthWeights>,
 }
 
-#[derive(Deserialize)]
-pub struct TogglePingArgs {
-    server_id: String,
-    address: String,
-    count: u32,
+// A run's settings and timing, for exporting it afterwards
+struct Session {
+    started_at_ms: u64,
+    ended_at_ms: Mutex<Option<u64>>,
+    args: TogglePingArgs,
+    // The ping invocation, empty for the backends that don't run one
+    command_line: Vec<String>,
 }
 
-// Parse ping output to extract latency
-fn parse_ping_line(line: &str) -> Option<f64> {
-    // Linux/macOS format: "64 bytes from x.x.x.x: icmp_seq=1 ttl=64 time=12.3 ms"
-    // Windows format: "Reply from x.x.x.x: bytes=32 time=12ms TTL=64"
+impl<R: Runtime> RunContext<R> {
+    // Reserve the server's slot before anything is spawned and clear its
+    // previous run's samples, unless this run continues `previous`; the
+    // caller holds the processes lock so the running check and this insert
+    // can't interleave with another toggle
+    fn reserve(
+        app: AppHandle<R>,
+        state: &PingManager,
+        processes: &mut HashMap<String, PingProcess>,
+        server_id: String,
+        flavor: PingFlavor,
+        previous: Option<&RunState>,
+    ) -> Self {
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let run = Arc::new(previous.map_or_else(RunState::default, RunState::carried_over));
+        let process = PingProcess {
+            child: None,
+            stop_flag: stop_flag.clone(),
+            run: run.clone(),
+            started_at: Instant::now(),
+        };
 
-    let re_unix = Regex::new(r"time[=<](\d+\.?\d*)\s*ms").ok()?;
-    let re_windows = Regex::new(r"time[=<](\d+)\s*ms").ok()?;
+        // Store the process
+        processes.insert(server_id.clone(), process);
+        if previous.is_none() {
+            state.samples.lock().remove(&server_id);
+        }
 
-    if let Some(caps) = re_unix.captures(line) {
-        return caps.get(1)?.as_str().parse().ok();
+        Self {
+            app,
+            server_id,
+            processes: state.processes.clone(),
+            samples: state.samples.clone(),
+            resolved_ips: state.resolved_ips.clone(),
+            last_good_ips: state.last_good_ips.clone(),
+            spawner: state.spawner.clone(),
+            stop_flag,
+            run,
+            rtt_summary: None,
+            alert_threshold_ms: None,
+            alerting: false,
+            session: None,
+            flavor,
+            batch_size: None,
+            batch: Vec::new(),
+            min_emit_interval: None,
+            last_emit_at: None,
+            notify_on_failure: false,
+            transitions_only: false,
+            failure_streak: 0,
+            highest_seq: None,
+            reorder_count: 0,
+            lost_seqs: VecDeque::new(),
+            echoes_done: 0,
+            echoes_total: None,
+            last_progress_at: None,
+            started_at: Instant::now(),
+            expected_ip: None,
+            unexpected_source: None,
+            output_ended: false,
+            ema_alpha: None,
+            ema_ms: None,
+            deadline: None,
+            health: None,
+        }
     }
 
-    if let Some(caps) = re_windows.captures(line) {
-        return caps.get(1)?.as_str().parse().ok();
+    // Hand a spawned child to the reserved slot, killing it if the run was
+    // stopped while it was starting
+    fn attach_child(&self, mut child: Child) {
+        let mut processes = self.processes.lock();
+        match processes
+            .get_mut(&self.server_id)
+            .filter(|process| Arc::ptr_eq(&process.run, &self.run))
+        {
+            Some(process) => {
+                // A failover replaces the previous address's ping
+                if let Some(mut previous) = process.child.replace(child) {
+                    let _ = previous.kill();
+                    let _ = previous.wait();
+                }
+            }
+            None => {
+                let _ = child.kill();
+            }
+        }
     }
 
-    None
-}
+    // Free the slot, unless a newer run for the same server has taken it,
+    // ending a ping that's still going because the reader finished first
+    fn release(&self) {
+        if let Some(child) = self.take_slot().and_then(|process| process.child) {
+            terminate_child(child);
+        }
+    }
 
-// Check if line indicates a timeout
-fn is_timeout_line(line: &str) -> bool {
-    let lower = line.to_lowercase();
-    lower.contains("request timed out")
-        || lower.contains("request timeout")
-        || lower.contains("100% packet loss")
-        || lower.contains("destination host unreachable")
-        || lower.contains("network is unreachable")
-}
+    fn take_slot(&self) -> Option<PingProcess> {
+        let mut processes = self.processes.lock();
+        if processes
+            .get(&self.server_id)
+            .is_some_and(|process| Arc::ptr_eq(&process.run, &self.run))
+        {
+            return processes.remove(&self.server_id);
+        }
+        None
+    }
 
-#[tauri::command]
-fn toggle_ping(
-    app: AppHandle,
-    state: State<'_, PingManager>,
-    args: TogglePingArgs,
-) -> Result<bool, String> {
-    let server_id = args.server_id.clone();
+    fn is_stopped(&self) -> bool {
+        // Only ever set, and nothing else is published through it
+        self.stop_flag.load(Ordering::Relaxed)
+    }
 
-    // Check if already running
-    {
-        let mut processes = state.processes.lock();
-        if let Some(mut process) = processes.remove(&server_id) {
-            // Stop the running process
-            *process.stop_flag.lock() = true;
-            let _ = process.child.kill();
+    fn is_paused(&self) -> bool {
+        *self.run.paused.lock()
+    }
+
+    // Apply the arguments' reporting options and open the run's session,
+    // timed from here, in the same spot its samples were cleared
+    fn configure(&mut self, state: &PingManager, args: &TogglePingArgs) {
+        self.alert_threshold_ms = args.alert_threshold_ms;
+        self.batch_size = args.batch_size;
+        self.min_emit_interval = args.min_emit_interval_ms.map(|ms| Duration::from_millis(ms.into()));
+        self.notify_on_failure = args.notify_on_failure;
+        self.transitions_only = args.transitions_only;
+        self.ema_alpha = args.ema_alpha;
+        self.deadline = args
+            .deadline_secs
+            .map(|secs| Instant::now() + Duration::from_secs(secs.into()));
+        self.echoes_total = args.count;
+        self.health = args.health.clone();
+
+        let command_line = if args.uses_ping_binary() {
+            ping_command_line(args, self.flavor)
+        } else {
+            Vec::new()
+        };
+        let session = Arc::new(Session {
+            started_at_ms: now_millis(),
+            ended_at_ms: Mutex::new(None),
+            args: args.clone(),
+            command_line,
+        });
+        state.sessions.lock().insert(self.server_id.clone(), session.clone());
+        self.session = Some(session);
+    }
+
+    // `slack` leaves a ping that enforces the deadline itself time to exit on
+    // its own and print its summary
+    fn past_deadline(&self, slack: Duration) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline + slack)
+    }
+
+    fn target_reached(&self, echoes: u32) -> bool {
+        self.run.target_count.lock().is_some_and(|target| echoes >= target)
+    }
+
+    // Replies still arriving after a stop are drained but not reported;
+    // `read_at` is when the reply was read or measured
+    fn reply(&mut self, reply: ParsedReply, read_at: ReadAt) {
+        if self.is_stopped() {
+            return;
+        }
+        self.count_echo();
+        if self.is_paused() {
+            return;
+        }
 
-            // Emit stopped event
-            let _ = app.emit("ping-stopped", PingEvent {
-                server_id: server_id.clone(),
+        let time_ms = reply.latency.time_ms;
+        let seq = reply.seq;
+        self.run.latency.lock().push(time_ms);
+        self.failure_streak = 0;
+        if let Some(alpha) = self.ema_alpha {
+            self.ema_ms = Some(match self.ema_ms {
+                Some(ema_ms) => alpha * time_ms + (1.0 - alpha) * ema_ms,
+                None => time_ms,
             });
+        }
+        let was_up = {
+            let mut last = self.run.last.lock();
+            last.time_ms = Some(time_ms);
+            last.replies += 1;
+            last.up.replace(true)
+        };
+        record_sample(&self.samples, &self.server_id, seq, Ok(time_ms), read_at.epoch_ms);
+
+        let result = PingResult {
+            server_id: self.server_id.clone(),
+            timestamp_ms: read_at.epoch_ms,
+            offset_ns: read_at.instant.saturating_duration_since(self.started_at).as_nanos() as u64,
+            time_ms,
+            time_us: (time_ms * 1000.0).round() as u64,
+            below_one_ms: reply.latency.below_one_ms,
+            ttl: reply.ttl,
+            seq,
+            bytes: reply.bytes,
+            from_ip: reply.from_ip.map(|ip| ip.to_string()),
+            ema_ms: self.ema_ms,
+        };
+        self.check_source(reply.from_ip);
+        self.check_order(seq);
+        self.run.push_recent(result.clone());
+        match self.batch_size {
+            _ if self.transitions_only => self.emit_status_change(was_up, true),
+            Some(batch_size) => {
+                self.batch.push(result);
+                if self.batch.len() >= batch_size as usize {
+                    self.flush_batch();
+                }
+            }
+            None => {
+                let due = self.min_emit_interval.is_none_or(|interval| {
+                    self.last_emit_at.is_none_or(|at| at.elapsed() >= interval)
+                });
+                if due {
+                    self.last_emit_at = Some(Instant::now());
+                    self.emit(EventName::Result, result);
+                }
+            }
+        }
 
-            return Ok(false); // Returning false means we stopped
+        if let Some(threshold_ms) = self.alert_threshold_ms {
+            let alerting = time_ms > threshold_ms;
+            if alerting != self.alerting {
+                self.alerting = alerting;
+                self.emit(EventName::Alert, PingAlert {
+                    server_id: self.server_id.clone(),
+                    time_ms,
+                    threshold_ms,
+                    alerting,
+                });
+            }
         }
+
+        self.emit_health();
     }
 
-    // Validate the address - only allow alphanumeric, dots, hyphens
-    let address = &args.address;
-    if !address.chars().all(|c| c.is_alphanumeric() || c == '.' || c == '-' || c == ':') {
-        return Err("Invalid address format".to_string());
+    fn failure(&mut self, kind: FailureKind, seq: Option<u32>) {
+        if self.is_stopped() {
+            return;
+        }
+        self.count_echo();
+        if self.is_paused() {
+            return;
+        }
+
+        record_sample(&self.samples, &self.server_id, seq, Err(kind), now_millis());
+        let was_up = {
+            let mut last = self.run.last.lock();
+            last.failures += 1;
+            last.up.replace(false)
+        };
+
+        if self.transitions_only {
+            self.emit_status_change(was_up, false);
+        } else {
+            self.emit(EventName::Failure, PingFailure {
+                server_id: self.server_id.clone(),
+                kind,
+            });
+            // Still sent for every kind so existing loss counting keeps working
+            self.emit(EventName::Timeout, PingTimeout {
+                server_id: self.server_id.clone(),
+            });
+        }
+
+        // Sent once per outage, when the streak first reaches the threshold
+        self.failure_streak += 1;
+        if self.notify_on_failure && self.failure_streak == NOTIFY_AFTER_FAILURES {
+            self.emit(EventName::Notify, PingNotify {
+                server_id: self.server_id.clone(),
+                failures: self.failure_streak,
+            });
+        }
+
+        self.emit_health();
     }
 
-    // Build ping command based on platform
-    #[cfg(target_os = "windows")]
-    let mut cmd = {
-        let mut c = Command::new("ping");
-        c.args(["-n", &args.count.to_string(), address]);
-        c
-    };
+    // Duplicates never get here, so a lower seq than seen before arrived out of order
+    fn check_order(&mut self, seq: Option<u32>) {
+        let Some(seq) = seq else {
+            return;
+        };
+        match self.highest_seq {
+            Some(highest) if seq_precedes(seq, highest) => {
+                self.reorder_count += 1;
+                self.emit(EventName::ReorderCount, PingReorderCount {
+                    server_id: self.server_id.clone(),
+                    count: self.reorder_count,
+                });
+            }
+            _ => self.highest_seq = Some(seq),
+        }
+    }
 
-    #[cfg(not(target_os = "windows"))]
-    let mut cmd = {
-        let mut c = Command::new("ping");
-        c.args(["-c", &args.count.to_string(), address]);
-        c
-    };
+    // Scored over the whole run so far, every HEALTH_EVERY echoes
+    fn emit_health(&self) {
+        let Some(weights) = &self.health else {
+            return;
+        };
+        if !self.echoes_done.is_multiple_of(HEALTH_EVERY) {
+            return;
+        }
 
-    cmd.stdout(Stdio::piped());
-    cmd.stderr(Stdio::piped());
+        let loss_percent = self
+            .run
+            .last
+            .lock()
+            .availability()
+            .map(|availability| (1.0 - availability) * 100.0);
+        let stats = self.run.latency.lock().summary(&self.server_id);
+        self.emit(EventName::Health, PingHealth {
+            server_id: self.server_id.clone(),
+            score: loss_percent.and_then(|loss| compute_health(&stats, loss, weights)),
+        });
+    }
 
-    // Prevent window creation on Windows
-    #[cfg(target_os = "windows")]
-    {
-        use std::os::windows::process::CommandExt;
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        cmd.creation_flags(CREATE_NO_WINDOW);
+    // Only a flip, or the first echo of the run, is a change
+    fn emit_status_change(&self, was_up: Option<bool>, up: bool) {
+        if was_up != Some(up) {
+            self.emit(EventName::StatusChange, PingStatusChange {
+                server_id: self.server_id.clone(),
+                up,
+            });
+        }
+    }
+
+    // Echoes count towards progress even while paused, since they still
+    // use up the run's count; the event itself is throttled
+    fn count_echo(&mut self) {
+        self.echoes_done += 1;
+        let due = self
+            .last_progress_at
+            .is_none_or(|at| at.elapsed() >= PROGRESS_INTERVAL);
+        if due && !self.is_paused() {
+            self.emit_progress();
+        }
     }
 
-    let mut child = cmd.spawn().map_err(|e| format!("Failed to spawn ping: {}", e))?;
-    let stdout = child.stdout.take().ok_or("Failed to get stdout")?;
+    fn emit_progress(&mut self) {
+        self.last_progress_at = Some(Instant::now());
+        self.emit(EventName::Progress, PingProgress {
+            server_id: self.server_id.clone(),
+            done: self.echoes_done,
+            total: self.echoes_total,
+        });
+    }
 
-    let stop_flag = Arc::new(Mutex::new(false));
-    let process = PingProcess {
-        child,
-        stop_flag: stop_flag.clone(),
-    };
+    fn emit<T: Serialize + Clone>(&self, event: EventName, payload: T) {
+        // Completion still goes out, or a hidden window would never learn
+        // the run ended
+        if self.run.emits() || matches!(event, EventName::Complete) {
+            emit_server(&self.app, event, &self.server_id, payload);
+        }
+    }
 
-    // Store the process
-    {
-        let mut processes = state.processes.lock();
-        processes.insert(server_id.clone(), process);
+    fn emit_rtt_summary(&self, rtt: Option<RttSummary>) {
+        if let Some(rtt) = rtt {
+            self.emit(EventName::RttSummary, PingRttSummary {
+                server_id: self.server_id.clone(),
+                rtt,
+            });
+        }
     }
 
-    // Spawn a thread to read stdout and emit events
-    let app_clone = app.clone();
-    let server_id_clone = server_id.clone();
-    let processes_clone = state.processes.clone();
+    fn emit_error(&self, message: String) {
+        self.emit(EventName::Error, PingError {
+            server_id: self.server_id.clone(),
+            message,
+        });
+    }
 
-    thread::spawn(move || {
-        let reader = BufReader::new(stdout);
+    // Sleep in short steps so a stop cuts the wait short, returning false if it did
+    fn sleep_unless_stopped(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        while !self.is_stopped() {
+            let now = Instant::now();
+            if now >= deadline {
+                return true;
+            }
+            thread::sleep(STOP_POLL_INTERVAL.min(deadline - now));
+        }
+        false
+    }
 
-        for line in reader.lines() {
-            // Check stop flag
-            if *stop_flag.lock() {
-                break;
+    // The same for runs driven from an async task
+    async fn sleep_unless_stopped_async(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        while !self.is_stopped() {
+            let now = Instant::now();
+            if now >= deadline {
+                return true;
             }
+            tokio::time::sleep(STOP_POLL_INTERVAL.min(deadline - now)).await;
+        }
+        false
+    }
 
-            if let Ok(line) = line {
-                // Try to parse ping result
-                if let Some(time_ms) = parse_ping_line(&line) {
-                    let _ = app_clone.emit("ping-result", PingResult {
-                        server_id: server_id_clone.clone(),
-                        time_ms,
-                    });
-                } else if is_timeout_line(&line) {
-                    let _ = app_clone.emit("ping-timeout", PingTimeout {
-                        server_id: server_id_clone.clone(),
-                    });
-                }
+    // When a backend that schedules its own echoes should send the next,
+    // `interval` from now but no later than the deadline
+    fn next_echo_at(&self, interval: Duration) -> Instant {
+        let due = Instant::now() + interval;
+        self.deadline.map_or(due, |deadline| deadline.min(due))
+    }
+
+    // Whether the wait for the next echo can end early, since `sent` echoes
+    // are all the run will send now
+    fn done_sending(&self, sent: u32) -> bool {
+        self.is_stopped() || self.target_reached(sent)
+    }
+
+    // Wait out the interval after `sent` echoes in short steps, so a stop
+    // or a new target count is acted on right away
+    fn wait_for_next_echo(&self, interval: Duration, sent: u32) {
+        let due = self.next_echo_at(interval);
+        while !self.done_sending(sent) {
+            let now = Instant::now();
+            if now >= due {
+                return;
             }
+            thread::sleep(STOP_POLL_INTERVAL.min(due - now));
         }
+    }
 
-        // Remove from processes map and emit complete
-        {
-            let mut processes = processes_clone.lock();
-            processes.remove(&server_id_clone);
+    async fn wait_for_next_echo_async(&self, interval: Duration, sent: u32) {
+        let due = self.next_echo_at(interval);
+        while !self.done_sending(sent) {
+            let now = Instant::now();
+            if now >= due {
+                return;
+            }
+            tokio::time::sleep(STOP_POLL_INTERVAL.min(due - now)).await;
+        }
+    }
+
+    // Remember the address's resolution, reporting it if it differs from last time
+    fn track_resolved_ip(&mut self, ip: IpAddr) {
+        self.expected_ip = Some(ip);
+        let previous = self.resolved_ips.lock().insert(self.server_id.clone(), ip);
+        if let Some(old_ip) = previous.filter(|old_ip| *old_ip != ip) {
+            self.emit(EventName::IpChanged, PingIpChanged {
+                server_id: self.server_id.clone(),
+                old_ip: old_ip.to_string(),
+                new_ip: ip.to_string(),
+            });
+        }
+    }
+
+    // A reply from somewhere else than was pinged points at NAT, asymmetric
+    // routing or spoofing
+    fn check_source(&mut self, from_ip: Option<IpAddr>) {
+        let (Some(expected), Some(actual)) = (self.expected_ip, from_ip) else {
+            return;
+        };
+        if actual == expected {
+            self.unexpected_source = None;
+        } else if self.unexpected_source != Some(actual) {
+            self.unexpected_source = Some(actual);
+            self.emit(EventName::UnexpectedSource, PingUnexpectedSource {
+                server_id: self.server_id.clone(),
+                expected: expected.to_string(),
+                actual: actual.to_string(),
+            });
+        }
+    }
+
+    fn emit_active_address(&self, address: &str) {
+        self.emit(EventName::ActiveAddress, PingActiveAddress {
+            server_id: self.server_id.clone(),
+            address: address.to_string(),
+        });
+    }
+
+    fn flush_batch(&mut self) {
+        if !self.batch.is_empty() {
+            let results = std::mem::take(&mut self.batch);
+            self.emit(EventName::Batch, PingBatch {
+                server_id: self.server_id.clone(),
+                results,
+            });
         }
+    }
 
-        // Only emit complete if we weren't stopped
-        if !*stop_flag.lock() {
-            let _ = app_clone.emit("ping-complete", PingEvent {
-                server_id: server_id_clone,
+    fn finish(mut self) {
+        // Remove from processes map and emit complete, collecting ping's
+        // exit code if it ended on its own
+        let exit_code = match self.take_slot().and_then(|process| process.child) {
+            Some(mut child) if self.output_ended => {
+                child.wait().ok().and_then(|status| status.code())
+            }
+            Some(child) => {
+                terminate_child(child);
+                None
+            }
+            None => None,
+        };
+        // Whatever is left of a batch goes out however the run ended
+        self.flush_batch();
+
+        // Only emit complete if we weren't stopped; a stopped run still
+        // reports the summary ping printed on its way out
+        if self.is_stopped() {
+            self.emit_rtt_summary(self.rtt_summary);
+        } else {
+            let stats = self.run.latency.lock().summary(&self.server_id);
+            let _ = record_history(&self.app, &stats);
+            // Prefer the OS's numbers, falling back on ours when it printed none
+            self.emit_rtt_summary(self.rtt_summary.or_else(|| RttSummary::from_stats(&stats)));
+            self.emit(EventName::Stats, stats);
+            // The last echoes may have fallen inside the throttle window
+            self.emit_progress();
+            self.emit(EventName::Complete, PingCompletion {
+                server_id: self.server_id.clone(),
+                exit_code,
+                // Either the run's own count or one set while it ran
+                reached_count: self.echoes_total.is_some_and(|total| self.echoes_done >= total)
+                    || self.target_reached(self.echoes_done),
+                deadline_reached: self.past_deadline(Duration::ZERO),
             });
         }
+    }
+}
+
+// Frees the slot and closes the session however a run ends, including
+// when it fails to start
+impl<R: Runtime> Drop for RunContext<R> {
+    fn drop(&mut self) {
+        self.release();
+        if let Some(session) = &self.session {
+            *session.ended_at_ms.lock() = Some(now_millis());
+        }
+    }
+}
+
+impl PingProcess {
+    fn stop(&mut self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
+        if let Some(child) = self.child.take() {
+            terminate_child(child);
+        }
+    }
+}
+
+// Ask ping to exit with SIGTERM so it prints its summary, and SIGKILL it
+// from a background thread if it hasn't exited within the grace period
+#[cfg(unix)]
+fn terminate_child(mut child: Child) {
+    // SAFETY: kill(2) has no memory effects; the pid is our unreaped child
+    let signalled = unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGTERM) } == 0;
+    if !signalled {
+        let _ = child.kill();
+        let _ = child.wait();
+        return;
+    }
+
+    thread::spawn(move || {
+        let deadline = Instant::now() + STOP_GRACE_PERIOD;
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        let _ = child.kill();
+        let _ = child.wait();
     });
+}
 
-    Ok(true) // Returning true means we started
+// Windows ping has nothing more to say once stopped, so just kill it
+#[cfg(not(unix))]
+fn terminate_child(mut child: Child) {
+    let _ = child.kill();
+    let _ = child.wait();
 }
 
-#[tauri::command]
-fn stop_all_pings(state: State<'_, PingManager>) {
-    let mut processes = state.processes.lock();
-    for (_, mut process) in processes.drain() {
-        *process.stop_flag.lock() = true;
-        let _ = process.child.kill();
+// Keeps a large server list from spawning hundreds of processes at once
+const DEFAULT_MAX_CONCURRENT: usize = 64;
+
+impl PingManager {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            processes: Arc::new(Mutex::new(HashMap::new())),
+            samples: Arc::new(Mutex::new(HashMap::new())),
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            resolved_ips: Arc::new(Mutex::new(HashMap::new())),
+            last_good_ips: Arc::new(Mutex::new(HashMap::new())),
+            max_concurrent,
+            flavors: Mutex::new(HashMap::new()),
+            spawner: Arc::new(CommandSpawner),
+            server_meta: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Use another way of starting ping, e.g. a fake replaying recorded output
+    pub fn with_spawner(mut self, spawner: Arc<dyn ProcessSpawner>) -> Self {
+        self.spawner = spawner;
+        self
+    }
+
+    // Kill every ping outright when the app quits; there's no time left for
+    // the SIGTERM grace period, and nobody is listening for events anymore
+    fn shutdown(&self) {
+        let processes: Vec<PingProcess> =
+            self.processes.lock().drain().map(|(_, process)| process).collect();
+        for mut process in processes {
+            process.stop_flag.store(true, Ordering::Relaxed);
+            if let Some(mut child) = process.child.take() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+
+    // Detect the ping implementation on first use of a binary and cache it
+    fn ping_flavor(&self, ping_path: Option<&Path>) -> PingFlavor {
+        let path = ping_path.unwrap_or(Path::new("ping"));
+        if let Some(flavor) = self.flavors.lock().get(path) {
+            return *flavor;
+        }
+
+        // Detected without the lock held, since it runs the binary
+        let flavor = detect_flavor(path);
+        self.flavors.lock().insert(path.to_path_buf(), flavor);
+        flavor
+    }
+
+    // The cached flavor, or the platform's usual one for a binary not run yet
+    fn known_flavor(&self, ping_path: Option<&Path>) -> PingFlavor {
+        let path = ping_path.unwrap_or(Path::new("ping"));
+        self.flavors.lock().get(path).copied().unwrap_or_else(platform_flavor)
+    }
+
+    // The flavor validated arguments start with; only a run of the ping
+    // binary is worth running it for
+    fn flavor_for(&self, args: &TogglePingArgs) -> PingFlavor {
+        if args.uses_ping_binary() {
+            self.ping_flavor(args.ping_path.as_deref())
+        } else {
+            self.known_flavor(args.ping_path.as_deref())
+        }
     }
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_opener::init())
-        .plugin(tauri_plugin_store::Builder::new().build())
-        .manage(PingManager::default())
-        .invoke_handler(tauri::generate_handler![toggle_ping, stop_all_pings])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+impl Default for PingManager {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONCURRENT)
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct PingResult {
+    server_id: String,
+    // When the reply was read, in Unix epoch millis, so graphs don't
+    // depend on when the UI got around to the event
+    timestamp_ms: u64,
+    // The same moment in nanoseconds since the run started, monotonic and
+    // fine-grained enough to space out fast pings
+    offset_ns: u64,
+    time_ms: f64,
+    // The same latency in whole microseconds, for frontends aggregating
+    // without float rounding
+    time_us: u64,
+    // Windows reported "time<1ms", so `time_ms` is only an upper bound
+    below_one_ms: bool,
+    ttl: Option<u32>,
+    seq: Option<u32>,
+    // Reply size and sender as printed by the ping binary
+    bytes: Option<u32>,
+    from_ip: Option<String>,
+    // Exponential moving average of the latency, when `ema_alpha` is set
+    ema_ms: Option<f64>,
+}
+
+// One echo of a run, successful or not, as retained for export
+#[derive(Clone, Serialize)]
+struct Sample {
+    timestamp_ms: u64,
+    seq: Option<u32>,
+    // `None` when the echo failed
+    time_ms: Option<f64>,
+    failure: Option<FailureKind>,
+}
+
+// Everything known about a server's latest run, for archiving
+#[derive(Serialize)]
+struct SessionExport<'a> {
+    server_id: &'a str,
+    started_at_ms: u64,
+    // `None` while the run is still going
+    ended_at_ms: Option<u64>,
+    config: &'a TogglePingArgs,
+    command_line: &'a [String],
+    stats: PingStats,
+    samples: Vec<&'a Sample>,
+    failures: Vec<&'a Sample>,
+}
+
+#[derive(Clone, Serialize)]
+struct PingTimeout {
+    server_id: String,
+}
+
+#[derive(Clone, Serialize)]
+struct PingFailure {
+    server_id: String,
+    kind: FailureKind,
+}
+
+#[derive(Clone, Serialize)]
+struct PingDnsResolved {
+    server_id: String,
+    resolved_ip: String,
+    resolve_ms: f64,
+}
+
+// DNS failed, so the server's last good IP is being pinged instead
+#[derive(Clone, Serialize)]
+struct PingDnsFallback {
+    server_id: String,
+    ip: String,
+    message: String,
+}
+
+// A router on the way to the server answered with an ICMP error
+#[derive(Clone, Serialize)]
+struct PingIcmpError {
+    server_id: String,
+    kind: IcmpErrorKind,
+    from_ip: Option<String>,
+}
+
+// Replies so far this run that arrived after one with a higher seq
+#[derive(Clone, Serialize)]
+struct PingReorderCount {
+    server_id: String,
+    count: u32,
+}
+
+// The ping binary is missing, so the run degraded to the configured fallback
+#[derive(Clone, Serialize)]
+struct PingFallbackUsed {
+    server_id: String,
+    missing: String,
+    fallback: PingFallback,
+}
+
+// A reply line whose latency is out of range, left out of the results
+#[derive(Clone, Serialize)]
+struct PingParseAnomaly {
+    server_id: String,
+    line: String,
+    value: String,
+}
+
+// The IP and payload size ping announced when it started; sent again for
+// each restart or failover. Kept apart from `ping-started`, which is sent
+// once per run for every backend
+#[derive(Clone, Serialize)]
+struct PingHeader {
+    server_id: String,
+    ip: String,
+    bytes: u32,
+}
+
+// A reply came from another address than the one being pinged
+#[derive(Clone, Serialize)]
+struct PingUnexpectedSource {
+    server_id: String,
+    expected: String,
+    actual: String,
+}
+
+// A server's address resolved differently than on its previous run
+#[derive(Clone, Serialize)]
+struct PingIpChanged {
+    server_id: String,
+    old_ip: String,
+    new_ip: String,
+}
+
+#[derive(Clone, Serialize)]
+struct PingPacketLoss {
+    server_id: String,
+    loss_percent: f64,
+}
+
+#[derive(Clone, Serialize)]
+struct PingStats {
+    server_id: String,
+    min_ms: Option<f64>,
+    max_ms: Option<f64>,
+    avg_ms: Option<f64>,
+    stddev_ms: Option<f64>,
+    // Mean absolute difference between consecutive replies
+    jitter_ms: Option<f64>,
+    samples: u32,
+}
+
+// How much latency, jitter and loss count towards the health score
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+struct HealthWeights {
+    latency: f64,
+    jitter: f64,
+    loss: f64,
+}
+
+impl Default for HealthWeights {
+    fn default() -> Self {
+        Self {
+            latency: 0.4,
+            jitter: 0.2,
+            loss: 0.4,
+        }
+    }
+}
+
+// Latency, jitter and loss each score 1 up to their "good" value, falling
+// linearly to 0 at their "bad" one; the score is their weighted average
+// scaled to 0-100. A run where nothing came back scores on loss alone,
+// so 0; `None` until there has been an echo at all
+const HEALTH_GOOD_LATENCY_MS: f64 = 50.0;
+const HEALTH_BAD_LATENCY_MS: f64 = 500.0;
+const HEALTH_GOOD_JITTER_MS: f64 = 5.0;
+const HEALTH_BAD_JITTER_MS: f64 = 100.0;
+const HEALTH_BAD_LOSS_PERCENT: f64 = 20.0;
+
+fn compute_health(stats: &PingStats, loss_percent: f64, weights: &HealthWeights) -> Option<u8> {
+    let component = |value: Option<f64>, good: f64, bad: f64| {
+        value.map_or(0.0, |value| (1.0 - (value - good) / (bad - good)).clamp(0.0, 1.0))
+    };
+    if stats.samples == 0 && loss_percent <= 0.0 {
+        return None;
+    }
+
+    let latency = component(stats.avg_ms, HEALTH_GOOD_LATENCY_MS, HEALTH_BAD_LATENCY_MS);
+    // A single reply has no jitter to penalize
+    let jitter = component(
+        stats.jitter_ms.or(stats.avg_ms.map(|_| 0.0)),
+        HEALTH_GOOD_JITTER_MS,
+        HEALTH_BAD_JITTER_MS,
+    );
+    let loss = component(Some(loss_percent), 0.0, HEALTH_BAD_LOSS_PERCENT);
+
+    let total = weights.latency + weights.jitter + weights.loss;
+    let score =
+        (weights.latency * latency + weights.jitter * jitter + weights.loss * loss) / total;
+    Some((score * 100.0).round() as u8)
+}
+
+impl RttSummary {
+    // Our own equivalent for runs without a summary line, if anything replied
+    fn from_stats(stats: &PingStats) -> Option<Self> {
+        Some(Self {
+            min_ms: stats.min_ms?,
+            avg_ms: stats.avg_ms?,
+            max_ms: stats.max_ms?,
+            mdev_ms: stats.stddev_ms,
+        })
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct PingRttSummary {
+    server_id: String,
+    #[serde(flatten)]
+    rtt: RttSummary,
+}
+
+// A finished run's stats as persisted in the history store
+#[derive(Clone, Serialize)]
+struct HistoryRecord {
+    timestamp_ms: u64,
+    #[serde(flatten)]
+    stats: PingStats,
+}
+
+#[derive(Clone, Serialize)]
+struct PingError {
+    server_id: String,
+    message: String,
+}
+
+// Latency crossed the alert threshold, upwards when `alerting` and back down otherwise
+#[derive(Clone, Serialize)]
+struct PingAlert {
+    server_id: String,
+    time_ms: f64,
+    threshold_ms: f64,
+    alerting: bool,
+}
+
+// A run finished by itself. `exit_code` is ping's, when one ran to the end:
+// on Linux 0 when everything was answered, 1 with losses and 2 on errors
+#[derive(Clone, Serialize)]
+struct PingCompletion {
+    server_id: String,
+    exit_code: Option<i32>,
+    reached_count: bool,
+    // The run was cut off by `deadline_secs`
+    deadline_reached: bool,
+}
+
+// How far a run is through its count; `total` is `None` when continuous
+#[derive(Clone, Serialize)]
+struct PingProgress {
+    server_id: String,
+    done: u32,
+    total: Option<u32>,
+}
+
+// A server's 0-100 health so far, `None` before there's anything to score
+#[derive(Clone, Serialize)]
+struct PingHealth {
+    server_id: String,
+    score: Option<u8>,
+}
+
+// A server went from down to up or back, or got its first result
+#[derive(Clone, Serialize)]
+struct PingStatusChange {
+    server_id: String,
+    up: bool,
+}
+
+// A server has failed enough echoes in a row to be worth an OS notification,
+// which the frontend shows
+#[derive(Clone, Serialize)]
+struct PingNotify {
+    server_id: String,
+    failures: u32,
+}
+
+// The address a server with several addresses is currently pinging
+#[derive(Clone, Serialize)]
+struct PingActiveAddress {
+    server_id: String,
+    address: String,
+}
+
+// A restart attempt of a ping that failed; also sent as `ping-restart-failed`
+// with the last attempt once they run out
+#[derive(Clone, Serialize)]
+struct PingRetry {
+    server_id: String,
+    attempt: u32,
+}
+
+// Ping went quiet: not even timeouts for `silent_for_ms`
+#[derive(Clone, Serialize)]
+struct PingStale {
+    server_id: String,
+    silent_for_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+struct PingBatch {
+    server_id: String,
+    results: Vec<PingResult>,
+}
+
+#[derive(Clone, Serialize)]
+struct PingDuplicate {
+    server_id: String,
+    seq: Option<u32>,
+}
+
+#[derive(Clone, Serialize)]
+struct PingRawLine {
+    server_id: String,
+    line: String,
+}
+
+// One running server as shown in the diagnostics panel
+#[derive(Clone, Serialize)]
+struct ServerSnapshot {
+    server_id: String,
+    sample_count: usize,
+    paused: bool,
+    uptime_ms: u64,
+}
+
+// The result of a validate-only toggle_ping
+#[derive(Clone, Serialize)]
+struct ConnectivityCheck {
+    server_id: String,
+    // `None` for HTTP probes, where the URL is resolved by the request
+    resolved_ip: Option<String>,
+    time_ms: f64,
+}
+
+// What toggle_ping did: still a bare `true`/`false` for started/stopped,
+// or the check's result for a count of zero
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ToggleOutcome {
+    Toggled(bool),
+    Checked(ConnectivityCheck),
+}
+
+#[derive(Clone, Serialize)]
+struct PingEvent {
+    server_id: String,
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct TogglePingArgs {
+    server_id: String,
+    #[serde(default)]
+    address: String,
+    // Addresses of one logical server in failover order; when given, the
+    // first replaces `address` and the rest take over if it stops answering
+    #[serde(default)]
+    addresses: Vec<String>,
+    // `None` pings continuously until stopped; `Some(0)` doesn't start
+    // anything, just resolves and pings once and returns the result
+    count: Option<u32>,
+    // Ping until stopped even when the global config sets a count
+    #[serde(default)]
+    continuous: bool,
+    interval_secs: Option<f64>,
+    packet_size: Option<u32>,
+    // Send echo requests over an ICMP socket instead of running `ping`
+    #[serde(default)]
+    use_native: bool,
+    // Force IPv4 or IPv6 resolution; `None` leaves the choice to the OS
+    ip_version: Option<IpVersion>,
+    // How long to wait for each reply before counting it lost
+    timeout_ms: Option<u32>,
+    // Time TCP connects to this port instead of sending ICMP, for hosts
+    // that drop pings
+    port: Option<u16>,
+    #[serde(default)]
+    mode: ProbeMode,
+    // Set the do-not-fragment bit, so oversized packets fail instead of
+    // being fragmented; lets the UI search for the path MTU
+    #[serde(default)]
+    dont_fragment: bool,
+    // Refuse to start when the address resolves to loopback, link-local or
+    // unspecified, which is usually a misconfiguration on remote monitors
+    #[serde(default)]
+    reject_special: bool,
+    // Also emit every line of ping output as-is, for debugging the parser
+    #[serde(default)]
+    emit_raw: bool,
+    // Emit `ping-alert` when replies cross above or back below this latency
+    alert_threshold_ms: Option<f64>,
+    // Outgoing interface name or source IP, for multi-homed machines
+    source: Option<String>,
+    // Run this binary instead of `ping` from PATH, e.g. a setuid wrapper
+    ping_path: Option<PathBuf>,
+    // What to run instead when the ping binary is missing
+    fallback: Option<PingFallback>,
+    // Run ping inside this named network namespace via `ip netns exec`;
+    // Linux only, and entering a namespace usually needs root
+    netns: Option<String>,
+    // Flood ping: send as fast as replies come back; Unix only and needs root
+    #[serde(default)]
+    flood: bool,
+    // Restart ping with exponential backoff if it fails to spawn or exits early
+    #[serde(default)]
+    auto_restart: bool,
+    // Send replies as `ping-batch` events of this many instead of `ping-result`
+    // each, for fast pings where per-reply IPC adds up
+    batch_size: Option<u32>,
+    // Weight of each new reply in `ema_ms`, in (0, 1]; higher follows faster
+    ema_alpha: Option<f64>,
+    // Emit `ping-health` every HEALTH_EVERY echoes, scored with these
+    // weights; `{}` uses the defaults
+    health: Option<HealthWeights>,
+    // Stop the run this long after it started, whatever its count
+    deadline_secs: Option<u32>,
+    // Send at most one `ping-result` per this many milliseconds, dropping
+    // the replies in between from the events but not from the stats
+    min_emit_interval_ms: Option<u32>,
+    // Emit `ping-stale` once ping has printed nothing for this long
+    stale_after_ms: Option<u32>,
+    // Flags passed to ping as-is after the generated ones, e.g. "-Q0x10";
+    // values must be attached to their flag. Flags that change the output
+    // format can break parsing
+    #[serde(default)]
+    extra_args: Vec<String>,
+    // Emit `ping-notify` once failures run on for NOTIFY_AFTER_FAILURES echoes
+    #[serde(default)]
+    notify_on_failure: bool,
+    // Only emit `ping-status-change` when the server flips between up and
+    // down, leaving out `ping-result`, `ping-failure` and `ping-timeout`
+    #[serde(default)]
+    transitions_only: bool,
+}
+
+// Defaults applied to every toggle_ping call for options it leaves unset
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct PingConfig {
+    // Calls ask for `continuous` to ping until stopped regardless
+    pub count: Option<u32>,
+    pub interval_secs: Option<f64>,
+    pub packet_size: Option<u32>,
+    pub timeout_ms: Option<u32>,
+    pub ping_path: Option<PathBuf>,
+    pub fallback: Option<PingFallback>,
+    // Largest count a run may ask for, DEFAULT_MAX_COUNT when unset
+    pub max_count: Option<u32>,
+    // Prepended to every event name, e.g. "bhpinger:" for "bhpinger:ping-result",
+    // so an embedding app's own events can't collide with them
+    pub event_prefix: Option<String>,
+}
+
+impl TogglePingArgs {
+    // Fill in anything the call didn't specify from the global config
+    fn apply_config(&mut self, config: &PingConfig) {
+        if !self.continuous {
+            self.count = self.count.or(config.count);
+        }
+        self.interval_secs = self.interval_secs.or(config.interval_secs);
+        self.packet_size = self.packet_size.or(config.packet_size);
+        self.timeout_ms = self.timeout_ms.or(config.timeout_ms);
+        self.ping_path = self.ping_path.take().or_else(|| config.ping_path.clone());
+        self.fallback = self.fallback.take().or_else(|| config.fallback.clone());
+    }
+
+    // Options only the ping binary understands are rejected for the other backends
+    fn uses_ping_binary(&self) -> bool {
+        self.mode == ProbeMode::Ping && self.port.is_none() && !self.use_native
+    }
+
+    // The addresses to ping in failover order, just `address` without a list
+    fn targets(&self) -> Vec<String> {
+        if self.addresses.is_empty() {
+            vec![self.address.clone()]
+        } else {
+            self.addresses.clone()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub enum ProbeMode {
+    // ICMP echo (or a TCP connect when `port` is set)
+    #[default]
+    Ping,
+    // HEAD requests to `address` as an http(s) URL
+    Http,
+}
+
+// For locked-down machines without a system ping
+#[derive(Clone, Deserialize, Serialize)]
+pub enum PingFallback {
+    // Run this binary instead, e.g. a static ping bundled with the app
+    Binary(PathBuf),
+    // Switch to the native ICMP backend; options only the ping binary
+    // supports are dropped
+    Native,
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum IpVersion {
+    V4,
+    V6,
+}
+
+// Longest soak test, which holds its command call open until it's done
+const MAX_SOAK_SECS: u32 = 3600;
+
+// Counts above this are almost certainly a UI bug, and would keep pinging
+// the target practically forever; continuous runs leave the count out
+const DEFAULT_MAX_COUNT: u32 = 100_000;
+
+// Unix ping refuses shorter intervals unless run as root; the other
+// backends never go below it
+const MIN_INTERVAL_SECS: f64 = 0.2;
+// An hour between echoes is already more a schedule than a ping
+const MAX_INTERVAL_SECS: f64 = 3600.0;
+
+// Largest payload accepted by both Windows and Unix ping
+const MAX_PACKET_SIZE: u32 = 65500;
+
+// Same payload size Unix ping sends by default
+const DEFAULT_PAYLOAD_SIZE: usize = 56;
+
+// Store file holding completed runs, keyed by server_id
+const HISTORY_STORE: &str = "ping-history.json";
+
+// Oldest runs are dropped once a server has this many stored
+const MAX_HISTORY_PER_SERVER: usize = 100;
+
+// How often a reader waiting on output rechecks its stop flag
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// How long a stopped ping gets to exit on its own before it is killed
+const STOP_GRACE_PERIOD: Duration = Duration::from_millis(500);
+
+// Consecutive lost echoes after which a server with several addresses
+// fails over to the next one
+const FAILOVER_AFTER_LOSSES: u32 = 3;
+
+// Restarts of a ping that failed or exited early wait this long, doubling
+// with each attempt, and give up after the maximum
+const RESTART_BASE_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_ATTEMPTS: u32 = 5;
+// A ping that ran this long before exiting earns a fresh set of attempts
+const RESTART_STABLE_AFTER: Duration = Duration::from_secs(60);
+
+// Consecutive failures after which `notify_on_failure` sends `ping-notify`
+const NOTIFY_AFTER_FAILURES: u32 = 3;
+
+// Echoes between `ping-health` events
+const HEALTH_EVERY: u32 = 10;
+
+// At most one `ping-progress` per this long, besides the final one
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+// Replies kept in memory for live graphs that remount
+const MAX_RECENT_RESULTS: usize = 120;
+
+// Lost echoes remembered for recognizing late replies, which only ever
+// trail the loss by a few echoes
+const MAX_LOST_SEQS: usize = 64;
+
+// Oldest samples are dropped once a run has recorded this many
+const MAX_SAMPLES_PER_SERVER: usize = 10_000;
+
+// Per-probe timeout for TCP and HTTP probes when no `timeout_ms` is given
+const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Gives each native pinger its own ICMP identifier
+static NEXT_ICMP_IDENT: AtomicU16 = AtomicU16::new(1);
+
+// Everything a native ICMP run needs once it has been validated
+struct NativePing {
+    client: Client,
+    ip: IpAddr,
+    count: Option<u32>,
+    interval: Duration,
+    timeout: Option<Duration>,
+    payload: Vec<u8>,
+}
+
+// Running totals over a run's successful latencies, so a summary costs the
+// same after a week of pinging as after a minute and nothing is kept per reply
+#[derive(Clone, Default)]
+struct LatencyStats {
+    samples: u32,
+    min_ms: f64,
+    max_ms: f64,
+    // Welford's running mean and sum of squared differences from it
+    mean_ms: f64,
+    m2: f64,
+    // The previous reply and the summed differences between consecutive ones
+    last_ms: Option<f64>,
+    jitter_sum_ms: f64,
+}
+
+impl LatencyStats {
+    fn push(&mut self, time_ms: f64) {
+        if self.samples == 0 {
+            self.min_ms = time_ms;
+            self.max_ms = time_ms;
+        } else {
+            self.min_ms = self.min_ms.min(time_ms);
+            self.max_ms = self.max_ms.max(time_ms);
+        }
+        self.samples += 1;
+        let delta = time_ms - self.mean_ms;
+        self.mean_ms += delta / f64::from(self.samples);
+        self.m2 += delta * (time_ms - self.mean_ms);
+        if let Some(last_ms) = self.last_ms.replace(time_ms) {
+            self.jitter_sum_ms += (time_ms - last_ms).abs();
+        }
+    }
+
+    // Leaves values empty when there were no replies
+    fn summary(&self, server_id: &str) -> PingStats {
+        let has_samples = self.samples > 0;
+        let n = f64::from(self.samples);
+        PingStats {
+            server_id: server_id.to_string(),
+            min_ms: has_samples.then_some(self.min_ms),
+            max_ms: has_samples.then_some(self.max_ms),
+            avg_ms: has_samples.then_some(self.mean_ms),
+            stddev_ms: has_samples.then(|| (self.m2 / n).sqrt()),
+            // Needs at least two replies to have a difference
+            jitter_ms: (self.samples >= 2).then(|| self.jitter_sum_ms / (n - 1.0)),
+            samples: self.samples,
+        }
+    }
+}
+
+// Summarize latencies collected elsewhere, like a finished run's samples
+fn compute_stats(server_id: &str, times: &[f64]) -> PingStats {
+    let mut stats = LatencyStats::default();
+    for &time_ms in times {
+        stats.push(time_ms);
+    }
+    stats.summary(server_id)
+}
+
+// Every event the backend emits, so the names are spelled out in one place
+#[derive(Clone, Copy)]
+enum EventName {
+    Started,
+    Stopped,
+    Restarted,
+    AllStopped,
+    Result,
+    Batch,
+    Failure,
+    Timeout,
+    Duplicate,
+    ReorderCount,
+    IcmpError,
+    ParseAnomaly,
+    RawLine,
+    Header,
+    PacketLoss,
+    RttSummary,
+    Stats,
+    Progress,
+    Complete,
+    Error,
+    Alert,
+    Notify,
+    Stale,
+    Health,
+    StatusChange,
+    Retry,
+    RestartFailed,
+    DnsResolved,
+    DnsFallback,
+    FallbackUsed,
+    IpChanged,
+    UnexpectedSource,
+    ActiveAddress,
+    TracerouteHop,
+    TracerouteComplete,
+}
+
+impl EventName {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Started => "ping-started",
+            Self::Stopped => "ping-stopped",
+            Self::Restarted => "ping-restarted",
+            Self::AllStopped => "ping-all-stopped",
+            Self::Result => "ping-result",
+            Self::Batch => "ping-batch",
+            Self::Failure => "ping-failure",
+            Self::Timeout => "ping-timeout",
+            Self::Duplicate => "ping-duplicate",
+            Self::ReorderCount => "ping-reorder-count",
+            Self::IcmpError => "ping-icmp-error",
+            Self::ParseAnomaly => "ping-parse-anomaly",
+            Self::RawLine => "ping-raw-line",
+            Self::Header => "ping-header",
+            Self::PacketLoss => "ping-packet-loss",
+            Self::RttSummary => "ping-rtt-summary",
+            Self::Stats => "ping-stats",
+            Self::Progress => "ping-progress",
+            Self::Complete => "ping-complete",
+            Self::Error => "ping-error",
+            Self::Alert => "ping-alert",
+            Self::Notify => "ping-notify",
+            Self::Stale => "ping-stale",
+            Self::Health => "ping-health",
+            Self::StatusChange => "ping-status-change",
+            Self::Retry => "ping-retry",
+            Self::RestartFailed => "ping-restart-failed",
+            Self::DnsResolved => "ping-dns-resolved",
+            Self::DnsFallback => "ping-dns-fallback",
+            Self::FallbackUsed => "ping-fallback-used",
+            Self::IpChanged => "ping-ip-changed",
+            Self::UnexpectedSource => "ping-unexpected-source",
+            Self::ActiveAddress => "ping-active-address",
+            Self::TracerouteHop => "traceroute-hop",
+            Self::TracerouteComplete => "traceroute-complete",
+        }
+    }
+}
+
+// Emit an event under its name with the configured prefix, if any
+fn emit_event<R: Runtime, T: Serialize + Clone>(app: &AppHandle<R>, event: EventName, payload: T) {
+    let name = match &app.state::<Mutex<PingConfig>>().lock().event_prefix {
+        Some(prefix) => format!("{}{}", prefix, event.as_str()),
+        None => event.as_str().to_string(),
+    };
+    let _ = app.emit(&name, payload);
+}
+
+// An event payload with the server's metadata added next to its own fields
+#[derive(Clone, Serialize)]
+struct WithMeta<T> {
+    #[serde(flatten)]
+    payload: T,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meta: Option<ServerMeta>,
+}
+
+// Emit one of a server's events, tagged with its metadata if it has any
+fn emit_server<R: Runtime, T: Serialize + Clone>(
+    app: &AppHandle<R>,
+    event: EventName,
+    server_id: &str,
+    payload: T,
+) {
+    let meta = app.state::<PingManager>().server_meta.lock().get(server_id).cloned();
+    emit_event(app, event, WithMeta { payload, meta });
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+// Append a completed run to the server's persisted history and save the store
+fn record_history<R: Runtime>(app: &AppHandle<R>, stats: &PingStats) -> Result<(), String> {
+    let store = app.store(HISTORY_STORE).map_err(|e| e.to_string())?;
+    let record = serde_json::to_value(HistoryRecord {
+        timestamp_ms: now_millis(),
+        stats: stats.clone(),
+    })
+    .map_err(|e| e.to_string())?;
+
+    let mut history = match store.get(&stats.server_id) {
+        Some(serde_json::Value::Array(entries)) => entries,
+        _ => Vec::new(),
+    };
+    history.push(record);
+    if history.len() > MAX_HISTORY_PER_SERVER {
+        history.drain(..history.len() - MAX_HISTORY_PER_SERVER);
+    }
+
+    store.set(stats.server_id.clone(), serde_json::Value::Array(history));
+    store.save().map_err(|e| e.to_string())
+}
+
+// Drop the samples and recent results kept in memory for a server. A running
+// server keeps going and keeps its session, so only what it recorded so far
+// is wiped, its totals included; otherwise the last run's session goes too
+fn forget_last_run(state: &PingManager, server_id: &str) {
+    // Held throughout so a run can't start between the check and the removal
+    let processes = state.processes.lock();
+    state.samples.lock().remove(server_id);
+    match processes.get(server_id) {
+        Some(process) => {
+            process.run.recent.lock().clear();
+            *process.run.latency.lock() = LatencyStats::default();
+            *process.run.last.lock() = LastResult::default();
+        }
+        None => {
+            state.sessions.lock().remove(server_id);
+        }
+    }
+}
+
+fn record_sample(
+    samples: &Mutex<HashMap<String, VecDeque<Sample>>>,
+    server_id: &str,
+    seq: Option<u32>,
+    outcome: Result<f64, FailureKind>,
+    timestamp_ms: u64,
+) {
+    let mut samples = samples.lock();
+    let history = samples.entry(server_id.to_string()).or_default();
+    if history.len() >= MAX_SAMPLES_PER_SERVER {
+        history.pop_front();
+    }
+    history.push_back(Sample {
+        timestamp_ms,
+        seq,
+        time_ms: outcome.ok(),
+        failure: outcome.err(),
+    });
+}
+
+
+// Strip URL-style brackets and decode an escaped zone separator,
+// so "[fe80::1%25eth0]" becomes "fe80::1%eth0"
+fn normalize_address(address: &str) -> String {
+    let unbracketed = address
+        .strip_prefix('[')
+        .and_then(|a| a.strip_suffix(']'))
+        .unwrap_or(address);
+    unbracketed.replacen("%25", "%", 1)
+}
+
+// Check for an IPv6 literal, optionally carrying a zone id ("fe80::1%eth0")
+fn is_ipv6_literal(address: &str) -> bool {
+    let ip = address.split_once('%').map_or(address, |(ip, _)| ip);
+    ip.parse::<Ipv6Addr>().is_ok()
+}
+
+// Reject a literal address whose family contradicts the requested version
+fn validate_ip_version(address: &str, version: Option<IpVersion>) -> Result<(), String> {
+    let address = normalize_address(address);
+    let is_v6 = is_ipv6_literal(&address);
+    let is_v4 = address.parse::<Ipv4Addr>().is_ok();
+
+    match version {
+        Some(IpVersion::V4) if is_v6 => Err("IPv6 address given but IPv4 was requested".to_string()),
+        Some(IpVersion::V6) if is_v4 => Err("IPv4 address given but IPv6 was requested".to_string()),
+        _ => Ok(()),
+    }
+}
+
+// HTTP probes take a full URL instead of a bare host
+fn validate_url(url: &str) -> Result<(), String> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .ok_or("URL must start with http:// or https://")?;
+    if rest.is_empty() || rest.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err("Invalid URL format".to_string());
+    }
+    Ok(())
+}
+
+// Validate the address - must be an IPv4/IPv6 literal or an RFC 1123 hostname
+fn validate_address(address: &str) -> Result<(), String> {
+    let address = normalize_address(address);
+    if address.parse::<IpAddr>().is_ok() {
+        return Ok(());
+    }
+
+    if let Some((ip, zone)) = address.split_once('%') {
+        let valid_zone = !zone.is_empty()
+            && zone
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+        if ip.parse::<Ipv6Addr>().is_ok() && valid_zone {
+            return Ok(());
+        }
+        return Err("Invalid address format".to_string());
+    }
+
+    if address.is_empty() || address.len() > 253 {
+        return Err("Invalid address format".to_string());
+    }
+
+    let labels: Vec<&str> = address.split('.').collect();
+    for label in &labels {
+        // Also rejects leading, trailing and repeated dots via empty labels
+        if label.is_empty() || label.len() > 63 {
+            return Err("Invalid address format".to_string());
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err("Invalid address format".to_string());
+        }
+        if !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err("Invalid address format".to_string());
+        }
+    }
+
+    // An all-numeric last label means a malformed IPv4 address, not a hostname
+    if labels.last().is_some_and(|label| label.chars().all(|c| c.is_ascii_digit())) {
+        return Err("Invalid address format".to_string());
+    }
+
+    Ok(())
+}
+
+// The ping implementation found on this system, which decides the flags it takes
+#[derive(Clone, Copy, PartialEq)]
+enum PingFlavor {
+    Iputils,
+    // macOS and the BSDs
+    Bsd,
+    // Alpine and other minimal images
+    Busybox,
+    // Debian's `inetutils-ping`: IPv4 only next to a `ping6`, like BSD, but
+    // with iputils' letters for what it does have
+    Inetutils,
+    Windows,
+}
+
+// The ping a platform usually has
+fn platform_flavor() -> PingFlavor {
+    if cfg!(target_os = "windows") {
+        PingFlavor::Windows
+    } else if cfg!(target_os = "linux") {
+        PingFlavor::Iputils
+    } else {
+        PingFlavor::Bsd
+    }
+}
+
+// Work out which ping a binary is; only Linux has more than one candidate
+fn detect_flavor(path: &Path) -> PingFlavor {
+    if cfg!(target_os = "windows") {
+        return PingFlavor::Windows;
+    }
+    if cfg!(target_os = "macos") {
+        return PingFlavor::Bsd;
+    }
+
+    // iputils and inetutils print their version for `-V`; BusyBox rejects
+    // it with its banner
+    let output = Command::new(path).arg("-V").stdin(Stdio::null()).output();
+    let text = output.map_or(String::new(), |output| {
+        let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+        text.push_str(&String::from_utf8_lossy(&output.stderr));
+        text
+    });
+    flavor_from_version(&text)
+}
+
+// Tell a ping apart by what it printed for `-V`; one that couldn't be run
+// or named itself some other way is taken to be the platform's usual one
+fn flavor_from_version(text: &str) -> PingFlavor {
+    let text = text.to_lowercase();
+    if text.contains("busybox") {
+        PingFlavor::Busybox
+    } else if text.contains("iputils") {
+        PingFlavor::Iputils
+    } else if text.contains("inetutils") {
+        PingFlavor::Inetutils
+    } else {
+        platform_flavor()
+    }
+}
+
+// Reject options the detected ping doesn't have, instead of letting it fail cryptically
+fn validate_flavor(args: &TogglePingArgs, flavor: PingFlavor) -> Result<(), String> {
+    if flavor == PingFlavor::Busybox && args.uses_ping_binary() {
+        if args.dont_fragment {
+            return Err("BusyBox ping doesn't support dont_fragment".to_string());
+        }
+        if args.flood {
+            return Err("BusyBox ping doesn't support flood".to_string());
+        }
+    }
+    if flavor == PingFlavor::Inetutils && args.uses_ping_binary() {
+        if args.dont_fragment {
+            return Err("GNU inetutils ping doesn't support dont_fragment".to_string());
+        }
+        if args.source.is_some() {
+            return Err("GNU inetutils ping doesn't support source".to_string());
+        }
+    }
+    if args.uses_ping_binary() {
+        for arg in &args.extra_args {
+            validate_extra_flags(arg, flavor)?;
+        }
+    }
+    Ok(())
+}
+
+// Option letters build_ping_command sets from typed arguments, plus preload,
+// which like flood needs root; setting them through extra arguments would
+// get around the count cap and the privilege check
+fn managed_flags(flavor: PingFlavor) -> &'static str {
+    match flavor {
+        PingFlavor::Iputils => "46cfiIlMsWw",
+        PingFlavor::Bsd => "bcDfilSstW",
+        PingFlavor::Busybox => "46ciIsWw",
+        PingFlavor::Inetutils => "cfilsWw",
+        PingFlavor::Windows => "46flnStw",
+    }
+}
+
+// Option letters that take a value, which is the rest of the argument
+// rather than more options
+fn value_flags(flavor: PingFlavor) -> &'static str {
+    match flavor {
+        PingFlavor::Iputils => "ceFiIlmMNpQsStTwW",
+        PingFlavor::Bsd => "BbcGghIiklMmpSsTtWz",
+        PingFlavor::Busybox => "cIipstwW",
+        PingFlavor::Inetutils => "cilpsTwW",
+        PingFlavor::Windows => "cijklnrsSvw",
+    }
+}
+
+// Walk a short option cluster like getopt would, so "-qf" is caught but a
+// pattern like "-pff" isn't mistaken for a flood; long options aren't wrapped
+fn validate_extra_flags(arg: &str, flavor: PingFlavor) -> Result<(), String> {
+    let Some(cluster) = arg.strip_prefix('-').filter(|rest| !rest.starts_with('-')) else {
+        return Ok(());
+    };
+    for letter in cluster.chars() {
+        if managed_flags(flavor).contains(letter) {
+            return Err(format!(
+                "Extra argument {:?} sets -{}, which only its own option may set",
+                arg, letter
+            ));
+        }
+        if value_flags(flavor).contains(letter) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// Build the platform-specific ping command for validated arguments
+fn build_ping_command(args: &TogglePingArgs, flavor: PingFlavor) -> Command {
+    // Windows ping detects IPv6 on its own, so it only needs the brackets
+    // and escaping removed
+    let address = normalize_address(&args.address);
+
+    // Build ping command based on platform
+    #[cfg(target_os = "windows")]
+    let mut cmd = {
+        let mut c = Command::new(ping_program(args, flavor));
+        match args.ip_version {
+            Some(IpVersion::V4) => {
+                c.arg("-4");
+            }
+            Some(IpVersion::V6) => {
+                c.arg("-6");
+            }
+            None => {}
+        }
+        match args.count {
+            Some(count) => c.args(["-n", &count.to_string()]),
+            None => c.arg("-t"),
+        };
+        if let Some(size) = args.packet_size {
+            c.args(["-l", &size.to_string()]);
+        }
+        if let Some(timeout) = args.timeout_ms {
+            c.args(["-w", &timeout.to_string()]);
+        }
+        if args.dont_fragment {
+            c.arg("-f");
+        }
+        if let Some(source) = &args.source {
+            c.args(["-S", source]);
+        }
+        c.args(&args.extra_args);
+        // Windows ping has no interval flag and always sends one echo per
+        // second, so validate_args turns away any other `interval_secs`;
+        // nor a deadline, which the reader enforces on its own
+        c.arg(&address);
+        c
+    };
+
+    #[cfg(not(target_os = "windows"))]
+    let mut cmd = {
+        let use_v6 = match args.ip_version {
+            Some(version) => version == IpVersion::V6,
+            None => is_ipv6_literal(&address),
+        };
+        let bsd = flavor == PingFlavor::Bsd;
+        let program = ping_program(args, flavor);
+        // The others pick the IP version with `ping6` instead
+        let versioned = matches!(flavor, PingFlavor::Iputils | PingFlavor::Busybox);
+        // `ip netns exec` and `stdbuf` each run the rest of the line
+        use std::ffi::OsStr;
+        let mut line: Vec<&OsStr> = Vec::new();
+        if let Some(netns) = &args.netns {
+            line.extend(["ip", "netns", "exec", netns].map(OsStr::new));
+        }
+        if let Some(stdbuf) = line_buffer_wrapper() {
+            line.extend([stdbuf.as_os_str(), OsStr::new("-oL")]);
+        }
+        line.push(program.as_os_str());
+        let mut c = Command::new(line[0]);
+        c.args(&line[1..]);
+        if versioned {
+            if use_v6 {
+                c.arg("-6");
+            } else if args.ip_version == Some(IpVersion::V4) {
+                c.arg("-4");
+            }
+        }
+        // A bare `ping host` loops until killed on both Linux and macOS,
+        // so continuous mode just leaves out `-c`
+        if let Some(count) = args.count {
+            c.args(["-c", &count.to_string()]);
+        }
+        // iputils prints nothing for an unanswered echo unless `-O` asks it
+        // to report "no answer yet" before sending the next one; failover,
+        // notifications and availability all count on seeing losses
+        if flavor == PingFlavor::Iputils {
+            c.arg("-O");
+        }
+        if let Some(interval) = args.interval_secs {
+            c.args(["-i", &interval.to_string()]);
+        }
+        if let Some(size) = args.packet_size {
+            c.args(["-s", &size.to_string()]);
+        }
+        // iputils and BusyBox `-W` take whole seconds, so sub-second timeouts
+        // round up; BSD `-W` is already in milliseconds
+        if let Some(timeout) = args.timeout_ms {
+            let timeout = if bsd { timeout } else { timeout.div_ceil(1000) };
+            c.args(["-W", &timeout.to_string()]);
+        }
+        if args.flood {
+            c.arg("-f");
+        }
+        // BSD's `-w` is the per-reply wait, its deadline is `-t`
+        if let Some(deadline) = args.deadline_secs {
+            c.args([if bsd { "-t" } else { "-w" }, &deadline.to_string()]);
+        }
+        // Inetutils can do neither this nor binding to a source, so
+        // validate_flavor turns both away
+        if args.dont_fragment {
+            if bsd {
+                c.arg("-D");
+            } else {
+                c.args(["-M", "do"]);
+            }
+        }
+        // BSD `-I` only applies to multicast, so it binds with `-S` for a
+        // source address and `-b` for an interface instead
+        if let Some(source) = &args.source {
+            if !bsd {
+                c.args(["-I", source]);
+            } else if source.parse::<IpAddr>().is_ok() {
+                c.args(["-S", source]);
+            } else {
+                c.args(["-b", source]);
+            }
+        }
+        c.args(&args.extra_args);
+        c.arg(&address);
+        c
+    };
+
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+    hide_window(&mut cmd);
+    cmd
+}
+
+// Prevent console window creation on Windows
+fn hide_window(cmd: &mut Command) {
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+    #[cfg(not(target_os = "windows"))]
+    let _ = cmd;
+}
+
+// The ping binary build_ping_command runs, inside any wrappers
+fn ping_program(args: &TogglePingArgs, flavor: PingFlavor) -> &Path {
+    // BSD and inetutils ping are IPv4 only and ship IPv6 support as `ping6`
+    let ping6 = matches!(flavor, PingFlavor::Bsd | PingFlavor::Inetutils)
+        && match args.ip_version {
+            Some(version) => version == IpVersion::V6,
+            None => is_ipv6_literal(&normalize_address(&args.address)),
+        };
+    let default_path = Path::new(if ping6 { "ping6" } else { "ping" });
+    args.ping_path.as_deref().unwrap_or(default_path)
+}
+
+// Where spawning a bare program name would find it
+fn find_in_path(name: &Path) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| {
+            let mut path = dir.join(name);
+            if cfg!(target_os = "windows") && path.extension().is_none() {
+                path.set_extension("exe");
+            }
+            path
+        })
+        .find(|path| path.is_file())
+}
+
+// Looked up ahead of spawning, since under `stdbuf` or `ip netns exec` a
+// missing ping only shows up as the wrapper failing
+fn ping_missing(program: &Path) -> bool {
+    if program.components().count() > 1 {
+        !program.is_file()
+    } else {
+        find_in_path(program).is_none()
+    }
+}
+
+// Pings that fully buffer stdout into a pipe only print their replies at
+// exit, so on Linux ping is run under `stdbuf -oL` when coreutils has it.
+// That works through LD_PRELOAD, which a setuid ping ignores, and there is
+// no equivalent on macOS; replies from such a ping can still arrive late
+#[cfg(not(target_os = "windows"))]
+fn line_buffer_wrapper() -> Option<&'static Path> {
+    static STDBUF: LazyLock<Option<PathBuf>> =
+        LazyLock::new(|| find_in_path(Path::new("stdbuf")));
+
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    STDBUF.as_deref()
+}
+
+// Give a missing ping binary an actionable message the frontend can show
+fn spawn_error(e: io::Error) -> String {
+    if e.kind() == io::ErrorKind::NotFound {
+        return "ping binary not found; install iputils-ping".to_string();
+    }
+    format!("Failed to spawn ping: {}", e)
+}
+
+// Start ping for validated arguments, reporting a missing binary as such
+// even when it would run under `stdbuf` or `ip netns exec`
+fn spawn_ping_process(
+    spawner: &dyn ProcessSpawner,
+    args: &TogglePingArgs,
+    flavor: PingFlavor,
+) -> Result<Spawned, String> {
+    if !spawner.can_run(ping_program(args, flavor)) {
+        return Err(spawn_error(io::ErrorKind::NotFound.into()));
+    }
+    spawner.spawn(build_ping_command(args, flavor)).map_err(spawn_error)
+}
+
+// Stop a single server's ping, returning whether one was running
+fn stop_server<R: Runtime>(app: &AppHandle<R>, manager: &PingManager, server_id: &str) -> bool {
+    let process = manager.processes.lock().remove(server_id);
+    let Some(process) = process else {
+        return false;
+    };
+
+    stop_process(app, manager, server_id, process);
+    true
+}
+
+// Stop a process already taken out of the map and tell listeners
+fn stop_process<R: Runtime>(
+    app: &AppHandle<R>,
+    manager: &PingManager,
+    server_id: &str,
+    mut process: PingProcess,
+) {
+    // Stop the running process
+    process.stop();
+    manager.last_good_ips.lock().remove(server_id);
+
+    // Emit stopped event
+    emit_server(app, EventName::Stopped, server_id, PingEvent {
+        server_id: server_id.to_string(),
+    });
+}
+
+// Cap finite runs; continuous ones have no count to check
+fn validate_count(count: Option<u32>, max_count: u32) -> Result<(), String> {
+    if count.is_some_and(|count| count > max_count) {
+        return Err(format!(
+            "Count must be at most {}; leave it out to ping continuously",
+            max_count
+        ));
+    }
+    Ok(())
+}
+
+// Check the timing and size options shared by toggle arguments and the global config
+fn validate_tuning(
+    interval_secs: Option<f64>,
+    timeout_ms: Option<u32>,
+    packet_size: Option<u32>,
+) -> Result<(), String> {
+    if let Some(interval) = interval_secs {
+        if !interval.is_finite() || interval <= 0.0 {
+            return Err("Interval must be a positive number of seconds".to_string());
+        }
+        if interval > MAX_INTERVAL_SECS {
+            return Err(format!("Interval must be at most {} seconds", MAX_INTERVAL_SECS));
+        }
+    }
+
+    if timeout_ms == Some(0) {
+        return Err("Timeout must be at least 1ms".to_string());
+    }
+
+    if let Some(size) = packet_size {
+        if !(1..=MAX_PACKET_SIZE).contains(&size) {
+            return Err(format!(
+                "Packet size must be between 1 and {} bytes",
+                MAX_PACKET_SIZE
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Whether the ping binary would refuse these options without root
+fn requires_privilege(args: &TogglePingArgs) -> bool {
+    // Windows ping has neither option, and the other backends don't need root for them
+    if cfg!(target_os = "windows") || !args.uses_ping_binary() {
+        return false;
+    }
+    args.flood || args.interval_secs.is_some_and(|interval| interval < MIN_INTERVAL_SECS)
+}
+
+#[cfg(unix)]
+fn is_root() -> bool {
+    // SAFETY: geteuid has no preconditions and can't fail
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_root() -> bool {
+    false
+}
+
+// Flag-shaped: one or two dashes and a letter or digit
+static RE_EXTRA_ARG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^-{1,2}[A-Za-z0-9]").unwrap());
+
+// Extra arguments must look like flags and carry nothing a shell would
+// interpret; no shell is involved, but it keeps them from smuggling in a
+// second target or anything surprising
+fn validate_extra_arg(arg: &str) -> Result<(), String> {
+    const SHELL_CHARS: &[char] = &[
+        ';', '&', '|', '$', '`', '<', '>', '(', ')', '{', '}', '[', ']', '*', '?', '!', '~',
+        '\'', '"', '\\',
+    ];
+    if !RE_EXTRA_ARG.is_match(arg) {
+        return Err(format!("Extra argument {:?} must be a flag like -x or --xyz", arg));
+    }
+    if arg.chars().any(|c| c.is_whitespace() || c.is_control() || SHELL_CHARS.contains(&c)) {
+        return Err(format!("Extra argument {:?} contains a disallowed character", arg));
+    }
+    Ok(())
+}
+
+// A custom ping binary must exist and be executable, checked before spawning
+// so a typo doesn't surface as a generic spawn failure
+fn validate_ping_path(path: &Path) -> Result<(), String> {
+    let metadata = std::fs::metadata(path)
+        .map_err(|e| format!("ping binary {} not found: {}", path.display(), e))?;
+    if !metadata.is_file() {
+        return Err(format!("ping binary {} is not a file", path.display()));
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Err(format!("ping binary {} is not executable", path.display()));
+        }
+    }
+
+    Ok(())
+}
+
+// A source must be an IP, or on Unix also an interface name like "eth0"
+fn validate_source(source: &str) -> Result<(), String> {
+    if source.parse::<IpAddr>().is_ok() {
+        return Ok(());
+    }
+
+    // Windows ping only binds to a source address
+    if cfg!(target_os = "windows") {
+        return Err("Source must be an IP address on Windows".to_string());
+    }
+
+    // Linux caps interface names at 15 bytes
+    let valid_name = !source.is_empty()
+        && source.len() <= 15
+        && source
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    if !valid_name {
+        return Err("Source must be an IP address or interface name".to_string());
+    }
+    Ok(())
+}
+
+// A namespace must be one `ip netns add` made, which names it after a file
+// under NETNS_DIR
+const NETNS_DIR: &str = "/run/netns";
+
+fn validate_netns(netns: &str) -> Result<(), String> {
+    if !cfg!(target_os = "linux") {
+        return Err("Network namespaces are only supported on Linux".to_string());
+    }
+
+    let valid_name = !netns.is_empty()
+        && netns.len() <= 255
+        && netns != "."
+        && netns != ".."
+        && netns
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    if !valid_name {
+        return Err(format!("Invalid network namespace name: {}", netns));
+    }
+
+    if !Path::new(NETNS_DIR).join(netns).exists() {
+        return Err(format!("Network namespace {} does not exist", netns));
+    }
+    Ok(())
+}
+
+// Check everything needed to start a ping before any process is spawned
+fn validate_args(args: &TogglePingArgs) -> Result<(), String> {
+    match args.mode {
+        ProbeMode::Ping => {
+            for address in args.targets() {
+                validate_address(&address)?;
+                validate_ip_version(&address, args.ip_version)?;
+            }
+        }
+        ProbeMode::Http => validate_url(&args.address)?,
+    }
+
+    validate_tuning(args.interval_secs, args.timeout_ms, args.packet_size)?;
+
+    if cfg!(target_os = "windows")
+        && args.uses_ping_binary()
+        && args.interval_secs.is_some_and(|interval| interval != 1.0)
+    {
+        return Err("Windows ping always sends one echo per second; use_native, port or \
+                    HTTP mode take other intervals"
+            .to_string());
+    }
+
+    // Without ping's own root check behind them, the backends that schedule
+    // their own echoes would flood the target or spin on shorter intervals
+    if !args.uses_ping_binary()
+        && args.interval_secs.is_some_and(|interval| interval < MIN_INTERVAL_SECS)
+    {
+        return Err(format!(
+            "Interval must be at least {}s with use_native, port or HTTP mode",
+            MIN_INTERVAL_SECS
+        ));
+    }
+
+    if args.continuous && args.count.is_some() {
+        return Err("continuous can't be combined with count".to_string());
+    }
+
+    if args
+        .alert_threshold_ms
+        .is_some_and(|threshold| !threshold.is_finite() || threshold <= 0.0)
+    {
+        return Err("Alert threshold must be a positive number of milliseconds".to_string());
+    }
+
+    if args
+        .ema_alpha
+        .is_some_and(|alpha| !(alpha > 0.0 && alpha <= 1.0))
+    {
+        return Err("EMA alpha must be greater than 0 and at most 1".to_string());
+    }
+
+    if let Some(weights) = &args.health {
+        let all = [weights.latency, weights.jitter, weights.loss];
+        if all.iter().any(|weight| !weight.is_finite() || *weight < 0.0)
+            || all.iter().sum::<f64>() <= 0.0
+        {
+            return Err("Health weights must be non-negative and not all zero".to_string());
+        }
+    }
+
+    if args.deadline_secs == Some(0) {
+        return Err("Deadline must be at least 1 second".to_string());
+    }
+
+    if args.batch_size == Some(0) {
+        return Err("Batch size must be at least 1".to_string());
+    }
+
+    if args.transitions_only && (args.batch_size.is_some() || args.min_emit_interval_ms.is_some()) {
+        return Err("transitions_only replaces per-result events, so it can't be combined with \
+                    batch_size or min_emit_interval_ms"
+            .to_string());
+    }
+
+    if let Some(interval_ms) = args.min_emit_interval_ms {
+        if interval_ms == 0 {
+            return Err("Minimum emit interval must be at least 1ms".to_string());
+        }
+        if args.batch_size.is_some() {
+            return Err("min_emit_interval_ms can't be combined with batch_size".to_string());
+        }
+    }
+
+    if !args.extra_args.is_empty() {
+        if !args.uses_ping_binary() {
+            return Err("extra_args is only supported when running the ping binary".to_string());
+        }
+        for arg in &args.extra_args {
+            validate_extra_arg(arg)?;
+        }
+    }
+
+    if args.stale_after_ms.is_some() && !args.uses_ping_binary() {
+        return Err("stale_after_ms is only supported when running the ping binary".to_string());
+    }
+
+    if args.auto_restart && !args.uses_ping_binary() {
+        return Err("auto_restart is only supported when running the ping binary".to_string());
+    }
+
+    if args.flood && (cfg!(target_os = "windows") || !args.uses_ping_binary()) {
+        return Err("Flood ping is only supported by the Unix ping binary".to_string());
+    }
+
+    // Caught here rather than left to ping's own cryptic refusal
+    if requires_privilege(args) && !is_root() {
+        return Err(format!(
+            "Flood ping and intervals under {}s need root privileges",
+            MIN_INTERVAL_SECS
+        ));
+    }
+
+    // A missing binary is left for the fallback to stand in for
+    if let Some(path) = &args.ping_path {
+        if args.uses_ping_binary() && !(args.fallback.is_some() && ping_missing(path)) {
+            validate_ping_path(path)?;
+        }
+    }
+
+    if let Some(source) = &args.source {
+        if !args.uses_ping_binary() {
+            return Err("source is only supported when running the ping binary".to_string());
+        }
+        validate_source(source)?;
+    }
+
+    if let Some(netns) = &args.netns {
+        if !args.uses_ping_binary() {
+            return Err("netns is only supported when running the ping binary".to_string());
+        }
+        validate_netns(netns)?;
+    }
+
+    if args.addresses.len() > 1 && !args.uses_ping_binary() {
+        return Err("Failover between addresses is only supported when running the ping binary".to_string());
+    }
+
+    if args.dont_fragment && !args.uses_ping_binary() {
+        return Err("dont_fragment is only supported when running the ping binary".to_string());
+    }
+
+    Ok(())
+}
+
+// Run a command's blocking part, like resolving names or waiting on ping,
+// on the blocking pool instead of tying up an async worker with it
+async fn run_blocking<T, F>(f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(|e| format!("Command failed to run: {}", e))?
+}
+
+// Async so a validate-only check or slow DNS doesn't block the main thread
+#[tauri::command]
+async fn toggle_ping(app: AppHandle, args: TogglePingArgs) -> Result<ToggleOutcome, String> {
+    run_blocking(move || toggle(&app, args)).await
+}
+
+fn toggle<R: Runtime>(
+    app: &AppHandle<R>,
+    mut args: TogglePingArgs,
+) -> Result<ToggleOutcome, String> {
+    let state = app.state::<PingManager>();
+    let config = app.state::<Mutex<PingConfig>>();
+    let server_id = args.server_id.clone();
+    let max_count = {
+        let config = config.lock();
+        args.apply_config(&config);
+        config.max_count.unwrap_or(DEFAULT_MAX_COUNT)
+    };
+    // With a failover list, everything up to the first spawn uses the primary
+    if let Some(primary) = args.addresses.first() {
+        args.address = primary.clone();
+    }
+
+    // A count of zero only checks the address resolves and answers once,
+    // whether or not the server is being monitored
+    if args.count == Some(0) {
+        validate_args(&args)?;
+        let flavor = state.flavor_for(&args);
+        validate_flavor(&args, flavor)?;
+        return check_connectivity(app, &state, args, flavor).map(ToggleOutcome::Checked);
+    }
+
+    // Validate before looking at running state, but only fail on start:
+    // stopping never uses the address, so a garbage one can still toggle off
+    let validation = validate_args(&args).and_then(|()| validate_count(args.count, max_count));
+
+    // Ping is only run to detect its flavor for arguments, ping_path
+    // included, that passed validation, and only when starting; the check
+    // is repeated under the lock below, which isn't held while ping runs
+    let starting = validation.is_ok() && !state.processes.lock().contains_key(&server_id);
+    let flavor = starting.then(|| state.flavor_for(&args));
+
+    // Stopping and reserving the slot happen under one lock, so two quick
+    // toggles for the same id can't both start a process
+    let mut ctx = {
+        let mut processes = state.processes.lock();
+
+        // Check if already running
+        if let Some(process) = processes.remove(&server_id) {
+            drop(processes);
+            stop_process(app, &state, &server_id, process);
+            return Ok(ToggleOutcome::Toggled(false)); // Returning false means we stopped
+        }
+
+        validation?;
+        // Only missing if a run stopped in between, which leaves it to be done here
+        let flavor = flavor.unwrap_or_else(|| state.flavor_for(&args));
+        validate_flavor(&args, flavor)?;
+
+        if processes.len() >= state.max_concurrent {
+            return Err("concurrency limit reached".to_string());
+        }
+
+        RunContext::reserve(app.clone(), &state, &mut processes, server_id.clone(), flavor, None)
+    };
+
+    // Lets other windows follow along without seeing the command's result;
+    // sent as soon as the slot is taken so it comes before any of the run's events
+    emit_server(app, EventName::Started, &server_id, PingEvent {
+        server_id: server_id.clone(),
+    });
+
+    ctx.configure(&state, &args);
+    if let Err(message) = start_run(ctx, args) {
+        // The slot is free again, so whoever saw it start sees it stop
+        emit_server(app, EventName::Stopped, &server_id, PingEvent {
+            server_id: server_id.clone(),
+        });
+        return Err(message);
+    }
+
+    Ok(ToggleOutcome::Toggled(true)) // Returning true means we started
+}
+
+// Start a running server over with the arguments it was started with,
+// keeping its samples and stats rolling instead of resetting them
+#[tauri::command]
+async fn restart_ping(app: AppHandle, server_id: String) -> Result<(), String> {
+    run_blocking(move || restart(&app, server_id)).await
+}
+
+fn restart<R: Runtime>(app: &AppHandle<R>, server_id: String) -> Result<(), String> {
+    let state = app.state::<PingManager>();
+    let not_running = || format!("{} isn't running", server_id);
+    let args = state
+        .sessions
+        .lock()
+        .get(&server_id)
+        .map(|session| session.args.clone())
+        .ok_or_else(not_running)?;
+    let flavor = state.flavor_for(&args);
+
+    // Swapped under one lock, so a toggle can't slip in between
+    let mut ctx = {
+        let mut processes = state.processes.lock();
+        let mut process = processes.remove(&server_id).ok_or_else(not_running)?;
+        process.stop();
+        let previous = Some(process.run.as_ref());
+        let id = server_id.clone();
+        RunContext::reserve(app.clone(), &state, &mut processes, id, flavor, previous)
+    };
+    ctx.configure(&state, &args);
+    if let Err(message) = start_run(ctx, args) {
+        // The old run is gone and the new one didn't start, as with toggle
+        emit_server(app, EventName::Stopped, &server_id, PingEvent {
+            server_id: server_id.clone(),
+        });
+        return Err(message);
+    }
+
+    emit_server(app, EventName::Restarted, &server_id, PingEvent {
+        server_id: server_id.clone(),
+    });
+    Ok(())
+}
+
+// The program and arguments toggle_ping would run for these arguments
+fn ping_command_line(args: &TogglePingArgs, flavor: PingFlavor) -> Vec<String> {
+    let cmd = build_ping_command(args, flavor);
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect()
+}
+
+// Validate the arguments like toggle_ping and return the ping invocation it
+// would start, without starting it
+#[tauri::command]
+fn preview_command(
+    state: State<'_, PingManager>,
+    config: State<'_, Mutex<PingConfig>>,
+    mut args: TogglePingArgs,
+) -> Result<String, String> {
+    let max_count = {
+        let config = config.lock();
+        args.apply_config(&config);
+        config.max_count.unwrap_or(DEFAULT_MAX_COUNT)
+    };
+    if let Some(primary) = args.addresses.first() {
+        args.address = primary.clone();
+    }
+
+    // Nothing is run for a preview, so a ping not used yet is taken to be
+    // the platform's usual one
+    validate_args(&args)?;
+    let flavor = state.known_flavor(args.ping_path.as_deref());
+    validate_flavor(&args, flavor)?;
+    validate_count(args.count, max_count)?;
+    if !args.uses_ping_binary() {
+        return Err("Only runs of the ping binary have a command line".to_string());
+    }
+
+    // Quoted only where needed, so it can be pasted into a shell
+    let parts: Vec<String> = ping_command_line(&args, flavor)
+        .into_iter()
+        .map(|part| {
+            if part.is_empty() || part.contains(char::is_whitespace) {
+                format!("\"{}\"", part)
+            } else {
+                part
+            }
+        })
+        .collect();
+    Ok(parts.join(" "))
+}
+
+// Resolve the address and probe it once with the backend a run with these
+// arguments would use
+fn check_connectivity<R: Runtime>(
+    app: &AppHandle<R>,
+    state: &PingManager,
+    mut args: TogglePingArgs,
+    mut flavor: PingFlavor,
+) -> Result<ConnectivityCheck, String> {
+    if args.mode == ProbeMode::Http {
+        let agent = ureq::AgentBuilder::new().timeout(probe_timeout(&args)).build();
+        let time_ms = http_head(&agent, &args.address).map_err(|_| "HTTP probe failed")?;
+        return Ok(ConnectivityCheck {
+            server_id: args.server_id,
+            resolved_ip: None,
+            time_ms,
+        });
+    }
+
+    let ip = resolve_timed(app, &args.server_id, &args)?;
+    let time_ms = match args.port {
+        Some(port) => tcp_connect(&SocketAddr::new(ip, port), probe_timeout(&args))
+            .map_err(|_| "TCP connect failed")?,
+        None if args.use_native => native_ping_once(&args, ip)?,
+        None => match use_fallback(state, &mut args, &mut flavor)? {
+            Some((_, PingFallback::Native)) => native_ping_once(&args, ip)?,
+            _ => {
+                args.count = Some(1);
+                ping_binary_once(state.spawner.as_ref(), &args, flavor)?
+            }
+        },
+    };
+
+    Ok(ConnectivityCheck {
+        server_id: args.server_id,
+        resolved_ip: Some(ip.to_string()),
+        time_ms,
+    })
+}
+
+// The fallback to use because the ping binary the arguments run isn't
+// installed, along with the one that's missing; a binary fallback is swapped
+// into `args` and `flavor` here. Runs and connectivity checks both go through
+// this, so they end up on the same backend
+fn use_fallback(
+    state: &PingManager,
+    args: &mut TogglePingArgs,
+    flavor: &mut PingFlavor,
+) -> Result<Option<(PathBuf, PingFallback)>, String> {
+    let Some(fallback) = args.fallback.clone() else {
+        return Ok(None);
+    };
+    let program = ping_program(args, *flavor).to_path_buf();
+    if state.spawner.can_run(&program) {
+        return Ok(None);
+    }
+
+    if let PingFallback::Binary(path) = &fallback {
+        validate_ping_path(path)?;
+        *flavor = state.ping_flavor(Some(path));
+        args.ping_path = Some(path.clone());
+    }
+    Ok(Some((program, fallback)))
+}
+
+// Start whichever backend the arguments ask for in the reserved slot; any
+// error drops `ctx`, which frees the slot again
+fn start_run<R: Runtime>(mut ctx: RunContext<R>, mut args: TogglePingArgs) -> Result<(), String> {
+    if args.mode == ProbeMode::Http {
+        start_http_probe(ctx, &args);
+        return Ok(());
+    }
+
+    // Resolve up front and report it separately, so slow DNS doesn't look
+    // like latency; nothing is started if it fails, unless an earlier
+    // resolution can stand in for it
+    let ip = match resolve_timed(&ctx.app, &ctx.server_id, &args) {
+        Ok(ip) => {
+            ctx.last_good_ips.lock().insert(ctx.server_id.clone(), ip);
+            ip
+        }
+        Err(message) => {
+            let fallback = ctx.last_good_ips.lock().get(&ctx.server_id).copied();
+            let Some(ip) = fallback else {
+                return Err(message);
+            };
+            ctx.emit(EventName::DnsFallback, PingDnsFallback {
+                server_id: ctx.server_id.clone(),
+                ip: ip.to_string(),
+                message,
+            });
+            // Ping would fail to resolve the name just the same
+            args.address = ip.to_string();
+            ip
+        }
+    };
+    ctx.track_resolved_ip(ip);
+
+    if args.reject_special {
+        if let Some(category) = special_address_category(&ip) {
+            return Err(format!("{} resolves to a {} address ({})", args.address, category, ip));
+        }
+    }
+
+    if let Some(port) = args.port {
+        start_tcp_ping(ctx, &args, SocketAddr::new(ip, port));
+        return Ok(());
+    }
+
+    if args.use_native {
+        return start_native_ping(ctx, &args, ip);
+    }
+
+    let fallback = use_fallback(&ctx.app.state::<PingManager>(), &mut args, &mut ctx.flavor)?;
+    if let Some((missing, fallback)) = fallback {
+        ctx.emit(EventName::FallbackUsed, PingFallbackUsed {
+            server_id: ctx.server_id.clone(),
+            missing: missing.display().to_string(),
+            fallback: fallback.clone(),
+        });
+        if let PingFallback::Native = fallback {
+            return start_native_ping(ctx, &args, ip);
+        }
+    }
+
+    // With auto_restart even the first spawn is retried from the reader
+    let mut line_rx = match spawn_ping(&mut ctx, &args) {
+        Ok(rx) => Some(rx),
+        Err(message) if args.auto_restart => {
+            ctx.emit_error(message);
+            None
+        }
+        Err(message) => return Err(message),
+    };
+    let targets = args.targets();
+    if targets.len() > 1 {
+        ctx.emit_active_address(&args.address);
+    }
+
+    // Parse output and emit events from a task, which only takes up one of
+    // the runtime's threads while a line is being handled
+    tauri::async_runtime::spawn(async move {
+        // Windows doesn't print sequence numbers, so count echoes ourselves
+        let mut echo_count: u32 = 0;
+        // Once stopped, keep reading until ping exits so the summary it
+        // prints on SIGTERM is still captured, but no longer than the grace period
+        let mut stopped_at: Option<Instant> = None;
+        let mut active = 0;
+        let mut loss_streak: u32 = 0;
+        // Respawns only send the echoes a counted run has left
+        let total_count = args.count;
+        let mut restart_attempt: u32 = 0;
+        let mut spawned_at = Instant::now();
+        // The current ping printed its closing summary, so it ran its course
+        let mut summary_seen = false;
+        // Reported once per silence, then again only after output resumes
+        let stale_after = args.stale_after_ms.map(|ms| Duration::from_millis(ms.into()));
+        let mut last_line_at = Instant::now();
+        let mut stale_reported = false;
+
+        loop {
+            // Check stop flag
+            if ctx.is_stopped()
+                && stopped_at.get_or_insert_with(Instant::now).elapsed() > STOP_GRACE_PERIOD
+            {
+                break;
+            }
+
+            if ctx.target_reached(echo_count) || ctx.past_deadline(STOP_GRACE_PERIOD) {
+                break;
+            }
+
+            // No ping running: it failed to spawn or exited early, so back off
+            // and start it again, for as long as attempts remain
+            let Some(rx) = &mut line_rx else {
+                if !args.auto_restart || ctx.is_stopped() {
+                    break;
+                }
+                if restart_attempt >= MAX_RESTART_ATTEMPTS {
+                    ctx.emit(EventName::RestartFailed, PingRetry {
+                        server_id: ctx.server_id.clone(),
+                        attempt: restart_attempt,
+                    });
+                    break;
+                }
+
+                restart_attempt += 1;
+                ctx.emit(EventName::Retry, PingRetry {
+                    server_id: ctx.server_id.clone(),
+                    attempt: restart_attempt,
+                });
+                let backoff = RESTART_BASE_DELAY * 2u32.pow(restart_attempt - 1);
+                if !ctx.sleep_unless_stopped_async(backoff).await {
+                    break;
+                }
+
+                args.count = total_count.map(|count| count.saturating_sub(echo_count));
+                spawned_at = Instant::now();
+                summary_seen = false;
+                match spawn_ping(&mut ctx, &args) {
+                    Ok(rx) => line_rx = Some(rx),
+                    Err(message) => ctx.emit_error(message),
+                }
+                continue;
+            };
+
+            let (read_at, line) = match tokio::time::timeout(STOP_POLL_INTERVAL, rx.recv()).await {
+                Ok(Some(PingOutput::Stdout(read_at, line))) => {
+                    last_line_at = Instant::now();
+                    stale_reported = false;
+                    (read_at, line)
+                }
+                // Errors like unresolvable hosts, passed on to the UI
+                Ok(Some(PingOutput::Stderr(message))) => {
+                    // Local MTU errors like "ping: local error: message too
+                    // long" only show up on stderr
+                    if classify_failure(&message) == Some(FailureKind::FragmentationNeeded) {
+                        ctx.emit(EventName::Failure, PingFailure {
+                            server_id: ctx.server_id.clone(),
+                            kind: FailureKind::FragmentationNeeded,
+                        });
+                    }
+                    ctx.emit_error(message);
+                    continue;
+                }
+                Err(_) => {
+                    let silent_for = last_line_at.elapsed();
+                    if stale_after.is_some_and(|window| silent_for > window)
+                        && !stale_reported
+                        && !ctx.is_paused()
+                    {
+                        stale_reported = true;
+                        ctx.emit(EventName::Stale, PingStale {
+                            server_id: ctx.server_id.clone(),
+                            silent_for_ms: silent_for.as_millis() as u64,
+                        });
+                    }
+                    continue;
+                }
+                Ok(None) => {
+                    // Exiting before a counted run finished, or at all when
+                    // continuous, is worth a restart; a finished run ends
+                    // with the summary even if losses went unreported
+                    let finished =
+                        total_count.is_some_and(|count| summary_seen || echo_count >= count);
+                    if args.auto_restart && !finished && !ctx.is_stopped() {
+                        if spawned_at.elapsed() >= RESTART_STABLE_AFTER {
+                            restart_attempt = 0;
+                        }
+                        line_rx = None;
+                        continue;
+                    }
+                    ctx.output_ended = true;
+                    break;
+                }
+            };
+
+            let line = match line {
+                Ok(line) => line,
+                // A failing pipe won't recover, so end the run as if ping had exited
+                Err(e) => {
+                    ctx.emit_error(format!("Failed to read ping output: {}", e));
+                    break;
+                }
+            };
+
+            if args.emit_raw && !ctx.is_paused() {
+                ctx.emit(EventName::RawLine, PingRawLine {
+                    server_id: ctx.server_id.clone(),
+                    line: line.clone(),
+                });
+            }
+
+            for update in parse_output_line(&line) {
+                match update {
+                    // Confirms what is being pinged before the first reply comes in
+                    PingUpdate::Header { ip, bytes } => {
+                        // Ping's own resolution is what replies answer for,
+                        // and can differ from ours under round-robin DNS
+                        if let Ok(pinged) = ip.parse() {
+                            ctx.expected_ip = Some(pinged);
+                        }
+                        ctx.emit(EventName::Header, PingHeader {
+                            server_id: ctx.server_id.clone(),
+                            ip,
+                            bytes,
+                        });
+                    }
+                    // Not another echo, so reported on its own and left out of the stats
+                    PingUpdate::Duplicate { seq } => {
+                        if !ctx.is_paused() {
+                            ctx.emit(EventName::Duplicate, PingDuplicate {
+                                server_id: ctx.server_id.clone(),
+                                seq,
+                            });
+                        }
+                    }
+                    PingUpdate::Reply(reply) => {
+                        // Already counted as lost when iputils stopped waiting for it
+                        if reply.seq.is_some_and(|seq| ctx.lost_seqs.contains(&seq)) {
+                            continue;
+                        }
+                        echo_count += 1;
+                        loss_streak = 0;
+                        ctx.reply(
+                            ParsedReply {
+                                seq: reply.seq.or(Some(echo_count)),
+                                ..reply
+                            },
+                            read_at,
+                        );
+                    }
+                    PingUpdate::Failure { kind, seq } => {
+                        echo_count += 1;
+                        loss_streak += 1;
+                        if let Some(seq) = seq {
+                            if ctx.lost_seqs.len() == MAX_LOST_SEQS {
+                                ctx.lost_seqs.pop_front();
+                            }
+                            ctx.lost_seqs.push_back(seq);
+                        }
+                        ctx.failure(kind, seq.or(Some(echo_count)));
+                    }
+                    PingUpdate::IcmpError { kind, from_ip } => {
+                        if !ctx.is_paused() {
+                            ctx.emit(EventName::IcmpError, PingIcmpError {
+                                server_id: ctx.server_id.clone(),
+                                kind,
+                                from_ip: from_ip.map(|ip| ip.to_string()),
+                            });
+                        }
+                    }
+                    PingUpdate::ParseAnomaly(value) => {
+                        if !ctx.is_paused() {
+                            ctx.emit(EventName::ParseAnomaly, PingParseAnomaly {
+                                server_id: ctx.server_id.clone(),
+                                line: line.clone(),
+                                value,
+                            });
+                        }
+                    }
+                    PingUpdate::RttSummary(rtt) => ctx.rtt_summary = Some(rtt),
+                    PingUpdate::PacketLoss(loss_percent) => {
+                        summary_seen = true;
+                        if !ctx.is_paused() {
+                            ctx.emit(EventName::PacketLoss, PingPacketLoss {
+                                server_id: ctx.server_id.clone(),
+                                loss_percent,
+                            });
+                        }
+                    }
+                    // Only ever read from stderr, which is forwarded on its own
+                    PingUpdate::Error(_) => {}
+                }
+            }
+
+            // Nothing is getting through, so move on to the next address,
+            // wrapping around so a recovered primary is picked up again
+            if targets.len() > 1 && loss_streak >= FAILOVER_AFTER_LOSSES && !ctx.is_stopped() {
+                active = (active + 1) % targets.len();
+                args.address = targets[active].clone();
+                // Failover targets aren't resolved here, so a hostname is only
+                // checked once the new ping's header names its address
+                ctx.expected_ip = normalize_address(&args.address).parse().ok();
+                ctx.unexpected_source = None;
+                args.count = total_count.map(|count| count.saturating_sub(echo_count));
+                loss_streak = 0;
+                spawned_at = Instant::now();
+                summary_seen = false;
+
+                match spawn_ping(&mut ctx, &args) {
+                    Ok(rx) => {
+                        line_rx = Some(rx);
+                        ctx.emit_active_address(&args.address);
+                    }
+                    Err(message) => {
+                        ctx.emit_error(message);
+                        line_rx = None;
+                    }
+                }
+            }
+        }
+
+        ctx.finish();
+    });
+
+    Ok(())
+}
+
+// Whether sequence number `a` came before `b`. icmp_seq is 16 bits and
+// wraps around in long runs, so they're compared as serial numbers
+// (RFC 1982): whichever is less than half the space behind the other is older
+fn seq_precedes(a: u32, b: u32) -> bool {
+    let behind = (b as u16).wrapping_sub(a as u16);
+    behind != 0 && behind < 0x8000
+}
+
+// What a running ping printed, as `read_output` hands it on
+enum PingOutput {
+    // A line of stdout and when it was read
+    Stdout(ReadAt, io::Result<String>),
+    // A line of stderr, trimmed and never empty
+    Stderr(String),
+}
+
+// When output was read, on both the wall clock and the monotonic one
+#[derive(Clone, Copy)]
+struct ReadAt {
+    epoch_ms: u64,
+    instant: Instant,
+}
+
+impl ReadAt {
+    fn now() -> Self {
+        Self {
+            epoch_ms: now_millis(),
+            instant: Instant::now(),
+        }
+    }
+}
+
+// Spawn ping for `args.address` in the run's slot and return its output
+fn spawn_ping<R: Runtime>(
+    ctx: &mut RunContext<R>,
+    args: &TogglePingArgs,
+) -> Result<UnboundedReceiver<PingOutput>, String> {
+    // A new ping counts its sequence numbers from the start again
+    ctx.highest_seq = None;
+    ctx.lost_seqs.clear();
+    let spawned = spawn_ping_process(ctx.spawner.as_ref(), args, ctx.flavor)?;
+    if let Some(child) = spawned.child {
+        ctx.attach_child(child);
+    }
+    Ok(read_output(spawned.stdout, spawned.stderr))
+}
+
+// Read both of ping's pipes into one channel, for the monitored runs and
+// `ping_stream` alike. Each pipe has its own task so a silent host can't
+// hold up the reader's stop flag check on the next line, and stdout lines
+// are timestamped there so a backed-up reader doesn't shift them
+fn read_output(
+    mut stdout: OutputReader,
+    stderr: Option<OutputReader>,
+) -> UnboundedReceiver<PingOutput> {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+
+    // Drained even once nobody listens, since ping dies writing to a closed pipe
+    if let Some(mut stderr) = stderr {
+        let tx = tx.clone();
+        spawn_reader(async move {
+            while let Some(Ok(line)) = next_output_line(&mut stderr).await {
+                let message = line.trim();
+                if !message.is_empty() {
+                    let _ = tx.send(PingOutput::Stderr(message.to_string()));
+                }
+            }
+        });
+    }
+
+    spawn_reader(async move {
+        while let Some(line) = next_output_line(&mut stdout).await {
+            // Nothing after a read error can be trusted, and it may repeat forever
+            let failed = line.is_err();
+            if tx.send(PingOutput::Stdout(ReadAt::now(), line)).is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    rx
+}
+
+// Pipes are read on the runtime `CommandSpawner` registered them with
+fn spawn_reader(reader: impl std::future::Future<Output = ()> + Send + 'static) {
+    match tokio::runtime::Handle::try_current() {
+        Ok(runtime) => {
+            runtime.spawn(reader);
+        }
+        Err(_) => {
+            tauri::async_runtime::spawn(reader);
+        }
+    }
+}
+
+// Read ping's next line like `AsyncBufReadExt::lines`, but decoding it for
+// the platform instead of failing on anything that isn't UTF-8; `None` once
+// the output ends
+async fn next_output_line(reader: &mut OutputReader) -> Option<io::Result<String>> {
+    let mut line = Vec::new();
+    match reader.read_until(b'\n', &mut line).await {
+        Ok(0) => None,
+        Ok(_) => {
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            Some(Ok(decode_output(&line)))
+        }
+        Err(e) => Some(Err(e)),
+    }
+}
+
+// Ping's lines for the one-off runs, which wait on them from a blocking thread
+fn output_lines(mut reader: OutputReader) -> impl Iterator<Item = io::Result<String>> {
+    std::iter::from_fn(move || tauri::async_runtime::block_on(next_output_line(&mut reader)))
+}
+
+// Windows ping writes in the console's OEM codepage, e.g. cp850 on a German
+// system, so localized lines have to be converted before they can be parsed
+#[cfg(target_os = "windows")]
+fn decode_output(bytes: &[u8]) -> String {
+    const CP_OEMCP: u32 = 1;
+    decode_codepage(bytes, CP_OEMCP)
+}
+
+// Convert text in a Windows codepage, falling back on lossy UTF-8 for
+// anything Windows can't convert
+#[cfg(target_os = "windows")]
+fn decode_codepage(bytes: &[u8], code_page: u32) -> String {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn MultiByteToWideChar(
+            code_page: u32,
+            flags: u32,
+            multi_byte: *const u8,
+            multi_byte_len: i32,
+            wide: *mut u16,
+            wide_len: i32,
+        ) -> i32;
+    }
+
+    // Plain ASCII, the usual case, reads the same in every codepage
+    if bytes.is_ascii() {
+        return String::from_utf8_lossy(bytes).into_owned();
+    }
+
+    let len = i32::try_from(bytes.len()).unwrap_or(i32::MAX);
+    // SAFETY: the input is `bytes` with its length; a null output only asks for the size
+    let wide_len =
+        unsafe { MultiByteToWideChar(code_page, 0, bytes.as_ptr(), len, std::ptr::null_mut(), 0) };
+    if wide_len <= 0 {
+        return String::from_utf8_lossy(bytes).into_owned();
+    }
+    let mut wide = vec![0u16; wide_len as usize];
+    // SAFETY: `wide` has room for the `wide_len` units the first call asked for
+    let written = unsafe {
+        MultiByteToWideChar(code_page, 0, bytes.as_ptr(), len, wide.as_mut_ptr(), wide_len)
+    };
+    String::from_utf16_lossy(&wide[..written.max(0) as usize])
+}
+
+// Everywhere else ping follows the locale, which is UTF-8 in practice; stray
+// invalid bytes are replaced rather than ending the run
+#[cfg(not(target_os = "windows"))]
+fn decode_output(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+// Resolve an address to a single IP, honoring a requested IP version
+fn resolve_address(address: &str, ip_version: Option<IpVersion>) -> Result<IpAddr, String> {
+    let ip = (normalize_address(address).as_str(), 0)
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve address: {}", e))?
+        .find(|addr| match ip_version {
+            Some(IpVersion::V4) => addr.is_ipv4(),
+            Some(IpVersion::V6) => addr.is_ipv6(),
+            None => true,
+        })
+        .ok_or("Failed to resolve address")?
+        .ip();
+    Ok(ip)
+}
+
+// Resolve the address, emitting how long it took or a failure if it didn't resolve
+fn resolve_timed<R: Runtime>(
+    app: &AppHandle<R>,
+    server_id: &str,
+    args: &TogglePingArgs,
+) -> Result<IpAddr, String> {
+    let started = Instant::now();
+    match resolve_address(&args.address, args.ip_version) {
+        Ok(ip) => {
+            emit_server(app, EventName::DnsResolved, server_id, PingDnsResolved {
+                server_id: server_id.to_string(),
+                resolved_ip: ip.to_string(),
+                resolve_ms: started.elapsed().as_secs_f64() * 1000.0,
+            });
+            Ok(ip)
+        }
+        Err(e) => {
+            emit_server(app, EventName::Failure, server_id, PingFailure {
+                server_id: server_id.to_string(),
+                kind: FailureKind::DnsFailure,
+            });
+            Err(e)
+        }
+    }
+}
+
+// Name the kind of address that never leaves the local host or link, if it is one
+fn special_address_category(ip: &IpAddr) -> Option<&'static str> {
+    if ip.is_loopback() {
+        return Some("loopback");
+    }
+    if ip.is_unspecified() {
+        return Some("unspecified");
+    }
+    let link_local = match ip {
+        IpAddr::V4(v4) => v4.is_link_local(),
+        // fe80::/10
+        IpAddr::V6(v6) => v6.segments()[0] & 0xffc0 == 0xfe80,
+    };
+    link_local.then_some("link-local")
+}
+
+// Time between probes for the backends that schedule their own echoes
+fn probe_interval(args: &TogglePingArgs) -> Duration {
+    // Validated to be in range; anything else falls back to the default
+    // rather than panicking
+    args.interval_secs
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .unwrap_or(Duration::from_secs(1))
+}
+
+// Per-probe timeout for the TCP and HTTP backends
+fn probe_timeout(args: &TogglePingArgs) -> Duration {
+    args.timeout_ms
+        .map_or(DEFAULT_PROBE_TIMEOUT, |ms| Duration::from_millis(ms.into()))
+}
+
+// Open an ICMP socket to the resolved address, then ping from an async task
+fn start_native_ping<R: Runtime>(
+    ctx: RunContext<R>,
+    args: &TogglePingArgs,
+    ip: IpAddr,
+) -> Result<(), String> {
+    let native = NativePing {
+        client: open_icmp_client(ip)?,
+        ip,
+        count: args.count,
+        interval: probe_interval(args),
+        timeout: native_timeout(args),
+        payload: native_payload(args),
+    };
+
+    tauri::async_runtime::spawn(run_native_ping(ctx, native));
+
+    Ok(())
+}
+
+fn open_icmp_client(ip: IpAddr) -> Result<Client, String> {
+    let config = match ip {
+        IpAddr::V4(_) => Config::default(),
+        IpAddr::V6(_) => Config::builder().kind(ICMP::V6).build(),
+    };
+
+    // The socket registers with the runtime's reactor, so it's created in
+    // the runtime's context; blocking on the runtime instead would panic
+    // when called from one of its threads. Failures here are usually
+    // missing raw socket privileges
+    let _runtime = tauri::async_runtime::handle().inner().enter();
+    Client::new(&config).map_err(|e| format!("Failed to open ICMP socket: {}", e))
+}
+
+// Left to surge-ping's default when not given
+fn native_timeout(args: &TogglePingArgs) -> Option<Duration> {
+    args.timeout_ms.map(|ms| Duration::from_millis(ms.into()))
+}
+
+fn native_payload(args: &TogglePingArgs) -> Vec<u8> {
+    vec![0; args.packet_size.map_or(DEFAULT_PAYLOAD_SIZE, |size| size as usize)]
+}
+
+// Send a single echo over an ICMP socket and wait for its reply, from a
+// blocking thread
+fn native_ping_once(args: &TogglePingArgs, ip: IpAddr) -> Result<f64, String> {
+    let client = open_icmp_client(ip)?;
+    let payload = native_payload(args);
+    let reply = tauri::async_runtime::block_on(async {
+        let ident = PingIdentifier(NEXT_ICMP_IDENT.fetch_add(1, Ordering::Relaxed));
+        let mut pinger = client.pinger(ip, ident).await;
+        if let Some(timeout) = native_timeout(args) {
+            pinger.timeout(timeout);
+        }
+        pinger.ping(PingSequence(0), &payload).await
+    });
+    reply
+        .map(|(_, rtt)| rtt.as_secs_f64() * 1000.0)
+        .map_err(|_| "Ping timed out".to_string())
+}
+
+async fn run_native_ping<R: Runtime>(mut ctx: RunContext<R>, native: NativePing) {
+    let ident = PingIdentifier(NEXT_ICMP_IDENT.fetch_add(1, Ordering::Relaxed));
+    let mut pinger = native.client.pinger(native.ip, ident).await;
+    if let Some(timeout) = native.timeout {
+        pinger.timeout(timeout);
+    }
+    let mut sent: u32 = 0;
+
+    while !ctx.is_stopped()
+        && !ctx.target_reached(sent)
+        && !ctx.past_deadline(Duration::ZERO)
+        && native.count.is_none_or(|count| sent < count)
+    {
+        // Paused runs stop sending echoes, picking the sequence up on resume
+        if ctx.is_paused() {
+            tokio::time::sleep(STOP_POLL_INTERVAL).await;
+            continue;
+        }
+
+        match pinger.ping(PingSequence(sent as u16), &native.payload).await {
+            // The reply TTL isn't surfaced by the socket, so it's left out
+            Ok((_, rtt)) => {
+                ctx.reply(ParsedReply::measured(rtt.as_secs_f64() * 1000.0, sent), ReadAt::now())
+            }
+            // Anything but a reply (usually the per-echo timeout) counts as lost
+            Err(_) => ctx.failure(FailureKind::Timeout, Some(sent)),
+        }
+
+        sent += 1;
+        if native.count.is_none_or(|count| sent < count) {
+            ctx.wait_for_next_echo_async(native.interval, sent).await;
+        }
+    }
+
+    ctx.finish();
+}
+
+// Time TCP handshakes to the resolved address from a thread
+fn start_tcp_ping<R: Runtime>(ctx: RunContext<R>, args: &TogglePingArgs, addr: SocketAddr) {
+    let timeout = probe_timeout(args);
+    let count = args.count;
+    let interval = probe_interval(args);
+
+    thread::spawn(move || run_probe_loop(ctx, count, interval, || tcp_connect(&addr, timeout)));
+}
+
+fn tcp_connect(addr: &SocketAddr, timeout: Duration) -> Result<f64, FailureKind> {
+    let started = Instant::now();
+    match TcpStream::connect_timeout(addr, timeout) {
+        Ok(_) => Ok(started.elapsed().as_secs_f64() * 1000.0),
+        Err(e) => Err(match e.kind() {
+            io::ErrorKind::ConnectionRefused => FailureKind::ConnectionRefused,
+            io::ErrorKind::HostUnreachable => FailureKind::HostUnreachable,
+            io::ErrorKind::NetworkUnreachable => FailureKind::NetUnreachable,
+            _ => FailureKind::Timeout,
+        }),
+    }
+}
+
+// Issue HEAD requests against the address as a URL from a thread
+fn start_http_probe<R: Runtime>(ctx: RunContext<R>, args: &TogglePingArgs) {
+    let timeout = probe_timeout(args);
+    let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+    let url = args.address.clone();
+    let count = args.count;
+    let interval = probe_interval(args);
+
+    thread::spawn(move || run_probe_loop(ctx, count, interval, || http_head(&agent, &url)));
+}
+
+// Time a HEAD request until its response headers arrive, roughly the time to first byte
+fn http_head(agent: &ureq::Agent, url: &str) -> Result<f64, FailureKind> {
+    let started = Instant::now();
+    match agent.head(url).call() {
+        Ok(response) if (200..300).contains(&response.status()) => {
+            Ok(started.elapsed().as_secs_f64() * 1000.0)
+        }
+        Ok(_) | Err(ureq::Error::Status(..)) => Err(FailureKind::HttpStatus),
+        Err(ureq::Error::Transport(transport)) => Err(match transport.kind() {
+            ureq::ErrorKind::Dns => FailureKind::HostUnreachable,
+            ureq::ErrorKind::ConnectionFailed => FailureKind::ConnectionRefused,
+            _ => FailureKind::Timeout,
+        }),
+    }
+}
+
+// Run a probe every interval until stopped or `count` probes were sent
+fn run_probe_loop<R: Runtime>(
+    mut ctx: RunContext<R>,
+    count: Option<u32>,
+    interval: Duration,
+    mut probe: impl FnMut() -> Result<f64, FailureKind>,
+) {
+    let mut sent: u32 = 0;
+
+    while !ctx.is_stopped()
+        && !ctx.target_reached(sent)
+        && !ctx.past_deadline(Duration::ZERO)
+        && count.is_none_or(|count| sent < count)
+    {
+        // Paused runs stop probing, picking the sequence up on resume
+        if ctx.is_paused() {
+            thread::sleep(STOP_POLL_INTERVAL);
+            continue;
+        }
+
+        sent += 1;
+        match probe() {
+            Ok(time_ms) => ctx.reply(ParsedReply::measured(time_ms, sent), ReadAt::now()),
+            Err(kind) => ctx.failure(kind, Some(sent)),
+        }
+
+        if count.is_none_or(|count| sent < count) {
+            ctx.wait_for_next_echo(interval, sent);
+        }
+    }
+
+    ctx.finish();
+}
+
+#[tauri::command]
+fn stop_all_pings(app: AppHandle, state: State<'_, PingManager>) {
+    let stopped: Vec<(String, PingProcess)> = state.processes.lock().drain().collect();
+    for (server_id, process) in stopped {
+        stop_process(&app, &state, &server_id, process);
+    }
+
+    emit_event(&app, EventName::AllStopped, ());
+}
+
+// Stop every server whose id starts with `prefix`, e.g. "prod-web-", and
+// return their ids; taken under one lock so runs completing meanwhile can't race
+#[tauri::command]
+fn stop_group(app: AppHandle, state: State<'_, PingManager>, prefix: String) -> Vec<String> {
+    let stopped: Vec<(String, PingProcess)> = {
+        let mut processes = state.processes.lock();
+        let ids: Vec<String> = processes
+            .keys()
+            .filter(|server_id| server_id.starts_with(&prefix))
+            .cloned()
+            .collect();
+        ids.into_iter()
+            .filter_map(|server_id| processes.remove(&server_id).map(|process| (server_id, process)))
+            .collect()
+    };
+
+    stopped
+        .into_iter()
+        .map(|(server_id, process)| {
+            stop_process(&app, &state, &server_id, process);
+            server_id
+        })
+        .collect()
+}
+
+// Delete a server's stored runs and the samples and results kept in memory;
+// a running ping carries on, recording from scratch
+#[tauri::command]
+fn clear_history(
+    app: AppHandle,
+    state: State<'_, PingManager>,
+    server_id: String,
+) -> Result<(), String> {
+    let store = app.store(HISTORY_STORE).map_err(|e| e.to_string())?;
+    store.delete(&server_id);
+    store.save().map_err(|e| e.to_string())?;
+
+    forget_last_run(&state, &server_id);
+    Ok(())
+}
+
+// Delete every server's stored runs and last-run samples
+#[tauri::command]
+fn clear_all_history(app: AppHandle, state: State<'_, PingManager>) -> Result<(), String> {
+    let store = app.store(HISTORY_STORE).map_err(|e| e.to_string())?;
+    store.clear();
+    store.save().map_err(|e| e.to_string())?;
+
+    let mut server_ids: Vec<String> = state.samples.lock().keys().cloned().collect();
+    server_ids.extend(state.sessions.lock().keys().cloned());
+    for server_id in server_ids {
+        forget_last_run(&state, &server_id);
+    }
+    Ok(())
+}
+
+// Export the latest run's samples as CSV with timestamp, seq, time_ms and status
+#[tauri::command]
+fn export_csv(state: State<'_, PingManager>, server_id: String) -> Result<String, String> {
+    let samples = state.samples.lock();
+    let history = samples
+        .get(&server_id)
+        .filter(|history| !history.is_empty())
+        .ok_or_else(|| format!("No samples recorded for {}", server_id))?;
+
+    let mut csv = String::from("timestamp,seq,time_ms,status\n");
+    for sample in history {
+        let seq = sample.seq.map(|seq| seq.to_string()).unwrap_or_default();
+        let (time_ms, status) = match sample.time_ms {
+            Some(time_ms) => (time_ms.to_string(), "ok"),
+            None => (String::new(), "timeout"),
+        };
+        csv.push_str(&format!("{},{},{},{}\n", sample.timestamp_ms, seq, time_ms, status));
+    }
+
+    Ok(csv)
+}
+
+// Export the latest run as JSON: its settings, timing, stats and every sample
+#[tauri::command]
+fn export_json(state: State<'_, PingManager>, server_id: String) -> Result<String, String> {
+    let session = state
+        .sessions
+        .lock()
+        .get(&server_id)
+        .cloned()
+        .ok_or_else(|| format!("No session recorded for {}", server_id))?;
+    let samples = state.samples.lock();
+    let history: Vec<&Sample> = samples.get(&server_id).into_iter().flatten().collect();
+
+    let times: Vec<f64> = history.iter().filter_map(|sample| sample.time_ms).collect();
+    let export = SessionExport {
+        server_id: &server_id,
+        started_at_ms: session.started_at_ms,
+        ended_at_ms: *session.ended_at_ms.lock(),
+        config: &session.args,
+        command_line: &session.command_line,
+        stats: compute_stats(&server_id, &times),
+        failures: history.iter().copied().filter(|sample| sample.failure.is_some()).collect(),
+        samples: history,
+    };
+
+    serde_json::to_string(&export).map_err(|e| e.to_string())
+}
+
+// Recent replies of a running server, oldest first, for redrawing graphs
+#[tauri::command]
+fn recent_samples(state: State<'_, PingManager>, server_id: String) -> Vec<PingResult> {
+    // Only hold the map lock long enough to grab the run's state
+    let run = state
+        .processes
+        .lock()
+        .get(&server_id)
+        .map(|process| process.run.clone());
+
+    run.map(|run| run.recent.lock().iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+// Attach a label and color to a server, or clear them with `None`; they're
+// kept whether or not it's running
+#[tauri::command]
+fn set_server_meta(state: State<'_, PingManager>, server_id: String, meta: Option<ServerMeta>) {
+    let mut server_meta = state.server_meta.lock();
+    match meta {
+        Some(meta) => {
+            server_meta.insert(server_id, meta);
+        }
+        None => {
+            server_meta.remove(&server_id);
+        }
+    }
+}
+
+// Replace the defaults merged into every toggle_ping call
+#[tauri::command]
+fn set_ping_config(state: State<'_, Mutex<PingConfig>>, config: PingConfig) -> Result<(), String> {
+    validate_tuning(config.interval_secs, config.timeout_ms, config.packet_size)?;
+    // Would turn every toggle into a validate-only check
+    if config.count == Some(0) {
+        return Err("Count must be at least 1".to_string());
+    }
+    if config.max_count == Some(0) {
+        return Err("Maximum count must be at least 1".to_string());
+    }
+    validate_count(config.count, config.max_count.unwrap_or(DEFAULT_MAX_COUNT))?;
+    if let Some(path) = &config.ping_path {
+        validate_ping_path(path)?;
+    }
+    if let Some(PingFallback::Binary(path)) = &config.fallback {
+        validate_ping_path(path)?;
+    }
+    // The characters Tauri allows in event names
+    if let Some(prefix) = &config.event_prefix {
+        if !prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '/' | ':' | '_'))
+        {
+            return Err("Event prefix may only contain letters, digits, -, /, : and _".to_string());
+        }
+    }
+    *state.lock() = config;
+    Ok(())
+}
+
+// Flip a running server's paused flag, returning whether it was running
+fn set_paused(state: &PingManager, server_id: &str, paused: bool) -> bool {
+    let processes = state.processes.lock();
+    let Some(process) = processes.get(server_id) else {
+        return false;
+    };
+    *process.run.paused.lock() = paused;
+    true
+}
+
+// Keep the process running but stop recording and emitting its results
+#[tauri::command]
+fn pause_ping(state: State<'_, PingManager>, server_id: String) -> bool {
+    set_paused(&state, &server_id, true)
+}
+
+#[tauri::command]
+fn resume_ping(state: State<'_, PingManager>, server_id: String) -> bool {
+    set_paused(&state, &server_id, false)
+}
+
+// Stop or resume a running server's events without pausing it, e.g. while
+// the window is hidden; returns whether it was running
+#[tauri::command]
+fn set_emission(state: State<'_, PingManager>, server_id: String, enabled: bool) -> bool {
+    let processes = state.processes.lock();
+    let Some(process) = processes.get(&server_id) else {
+        return false;
+    };
+    process.run.emit_enabled.store(enabled, Ordering::Relaxed);
+    true
+}
+
+// Make a running server complete once it has sent `count` echoes in total,
+// right away if it already has; returns whether it was running
+#[tauri::command]
+fn set_target_count(state: State<'_, PingManager>, server_id: String, count: u32) -> bool {
+    let processes = state.processes.lock();
+    let Some(process) = processes.get(&server_id) else {
+        return false;
+    };
+    *process.run.target_count.lock() = Some(count);
+    true
+}
+
+// Stop one server without toggling, so the caller needn't know its state
+#[tauri::command]
+fn stop_ping(app: AppHandle, state: State<'_, PingManager>, server_id: String) -> bool {
+    stop_server(&app, &state, &server_id)
+}
+
+// One address's result in a soak test; `server_id` in the stats is the address
+#[derive(Clone, Serialize)]
+struct SoakReport {
+    #[serde(flatten)]
+    stats: PingStats,
+    sent: u32,
+    loss_percent: f64,
+}
+
+// Ping every address once a second for `duration_secs`, all at once, and
+// return them ranked by loss and then average latency. Runs outside the
+// monitored servers, so it emits no events and takes no slots
+#[tauri::command]
+async fn soak_test(
+    app: AppHandle,
+    addresses: Vec<String>,
+    duration_secs: u32,
+) -> Result<Vec<SoakReport>, String> {
+    // Waits up to MAX_SOAK_SECS, far too long to hold an async worker
+    run_blocking(move || soak(&app.state::<PingManager>(), addresses, duration_secs)).await
+}
+
+fn soak(
+    state: &PingManager,
+    addresses: Vec<String>,
+    duration_secs: u32,
+) -> Result<Vec<SoakReport>, String> {
+    if addresses.is_empty() {
+        return Err("No addresses to soak test".to_string());
+    }
+    if addresses.len() > state.max_concurrent {
+        return Err("concurrency limit reached".to_string());
+    }
+    if duration_secs == 0 || duration_secs > MAX_SOAK_SECS {
+        return Err(format!("Duration must be between 1 and {} seconds", MAX_SOAK_SECS));
+    }
+    for address in &addresses {
+        validate_address(address)?;
+    }
+
+    let flavor = state.ping_flavor(None);
+    let handles: Vec<_> = addresses
+        .into_iter()
+        .map(|address| {
+            let spawner = state.spawner.clone();
+            thread::spawn(move || soak_address(spawner.as_ref(), address, duration_secs, flavor))
+        })
+        .collect();
+    let mut reports = handles
+        .into_iter()
+        .map(|handle| handle.join().map_err(|_| "Soak test thread panicked".to_string())?)
+        .collect::<Result<Vec<_>, String>>()?;
+
+    reports.sort_by(|a, b| {
+        a.loss_percent.total_cmp(&b.loss_percent).then_with(|| {
+            let avg = |report: &SoakReport| report.stats.avg_ms.unwrap_or(f64::INFINITY);
+            avg(a).total_cmp(&avg(b))
+        })
+    });
+    Ok(reports)
+}
+
+// Run one soak test ping to completion; unanswered echoes are what's left of
+// the count, since Unix ping doesn't report them line by line
+fn soak_address(
+    spawner: &dyn ProcessSpawner,
+    address: String,
+    duration_secs: u32,
+    flavor: PingFlavor,
+) -> Result<SoakReport, String> {
+    let args = TogglePingArgs {
+        address,
+        count: Some(duration_secs),
+        ..Default::default()
+    };
+    let spawned = spawn_ping_process(spawner, &args, flavor)?;
+
+    let times: Vec<f64> = output_lines(spawned.stdout)
+        .map_while(Result::ok)
+        .filter(|line| !is_duplicate_line(line))
+        .filter_map(|line| parse_ping_reply(&line))
+        .map(|reply| reply.latency.time_ms)
+        .collect();
+    if let Some(mut child) = spawned.child {
+        let _ = child.wait();
+    }
+
+    let received = (times.len() as u32).min(duration_secs);
+    Ok(SoakReport {
+        stats: compute_stats(&args.address, &times),
+        sent: duration_secs,
+        loss_percent: f64::from(duration_secs - received) * 100.0 / f64::from(duration_secs),
+    })
+}
+
+// Something ping reported, as received from `ping_stream`
+pub enum PingUpdate {
+    // The address and payload size ping announces before the first reply
+    Header { ip: String, bytes: u32 },
+    Reply(ParsedReply),
+    // Another reply to an echo that was already answered
+    Duplicate { seq: Option<u32> },
+    // An echo that won't be answered, with its sequence number if ping printed one
+    Failure { kind: FailureKind, seq: Option<u32> },
+    IcmpError { kind: IcmpErrorKind, from_ip: Option<IpAddr> },
+    // A latency that was printed but couldn't be believed, as printed
+    ParseAnomaly(String),
+    RttSummary(RttSummary),
+    PacketLoss(f64),
+    // A line ping printed on stderr
+    Error(String),
+}
+
+// Everything a line of ping's stdout reports, in the order to handle it;
+// shared by the monitored runs and `ping_stream` so both read ping alike
+fn parse_output_line(line: &str) -> Vec<PingUpdate> {
+    let mut updates = Vec::new();
+    if let Some((ip, bytes)) = parse_ping_header(line) {
+        updates.push(PingUpdate::Header { ip, bytes });
+    }
+
+    if is_duplicate_line(line) {
+        updates.push(PingUpdate::Duplicate {
+            seq: parse_ping_seq(line),
+        });
+    } else if let Some(reply) = parse_ping_reply(line) {
+        updates.push(PingUpdate::Reply(reply));
+    } else if let Some(kind) = classify_failure(line) {
+        updates.push(PingUpdate::Failure {
+            kind,
+            seq: parse_ping_seq(line),
+        });
+    } else if let Some(kind) = classify_icmp_error(line) {
+        updates.push(PingUpdate::IcmpError {
+            kind,
+            from_ip: parse_ping_from(line),
+        });
+        // A redirect is only advice and the echo still gets through,
+        // but with the TTL exceeded it never arrives
+        if kind == IcmpErrorKind::TtlExceeded {
+            updates.push(PingUpdate::Failure {
+                kind: FailureKind::TtlExceeded,
+                seq: parse_ping_seq(line),
+            });
+        }
+    } else if let Some(value) = parse_latency_anomaly(line) {
+        updates.push(PingUpdate::ParseAnomaly(value));
+    }
+
+    if let Some(rtt) = parse_rtt_summary(line) {
+        updates.push(PingUpdate::RttSummary(rtt));
+    }
+    if let Some(loss_percent) = parse_packet_loss(line) {
+        updates.push(PingUpdate::PacketLoss(loss_percent));
+    }
+    updates
+}
+
+// Run ping for use from async Rust code outside of Tauri, reading its output
+// the way the monitored runs do. The stream ends once ping exits
+pub async fn ping_stream(address: &str, config: &PingConfig) -> Result<PingStream, String> {
+    validate_address(address)?;
+    validate_tuning(config.interval_secs, config.timeout_ms, config.packet_size)?;
+    let mut args = TogglePingArgs {
+        address: address.to_string(),
+        ..Default::default()
+    };
+    args.apply_config(config);
+    if args.count == Some(0) {
+        return Err("Count must be at least 1".to_string());
+    }
+    validate_count(args.count, config.max_count.unwrap_or(DEFAULT_MAX_COUNT))?;
+    if let Some(path) = &args.ping_path {
+        validate_ping_path(path)?;
+    }
+
+    // Detecting the flavor waits on `ping -V`, so it's kept off the caller's worker
+    let spawned = tokio::task::spawn_blocking(move || {
+        let flavor = detect_flavor(args.ping_path.as_deref().unwrap_or(Path::new("ping")));
+        spawn_ping_process(&CommandSpawner, &args, flavor)
+    })
+    .await
+    .map_err(|e| format!("Failed to spawn ping: {}", e))??;
+
+    Ok(PingStream {
+        output: read_output(spawned.stdout, spawned.stderr),
+        pending: VecDeque::new(),
+        child: spawned.child,
+    })
+}
+
+// Ping's output parsed as it comes, from `ping_stream`; dropping it stops
+// ping right away, even on a host that has gone silent
+pub struct PingStream {
+    output: UnboundedReceiver<PingOutput>,
+    // What the last line reported that hasn't been handed out yet
+    pending: VecDeque<PingUpdate>,
+    child: Option<Child>,
+}
+
+impl tokio_stream::Stream for PingStream {
+    type Item = PingUpdate;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<PingUpdate>> {
+        loop {
+            if let Some(update) = self.pending.pop_front() {
+                return Poll::Ready(Some(update));
+            }
+            match ready!(self.output.poll_recv(cx)) {
+                Some(PingOutput::Stdout(_, Ok(line))) => {
+                    let updates = parse_output_line(&line);
+                    self.pending.extend(updates);
+                }
+                Some(PingOutput::Stderr(message)) => {
+                    return Poll::Ready(Some(PingUpdate::Error(message)))
+                }
+                // A failed read ends the stream like ping exiting would
+                Some(PingOutput::Stdout(_, Err(_))) | None => {
+                    // Collected now, not left a zombie for as long as the stream is kept
+                    if let Some(child) = self.child.take() {
+                        terminate_child(child);
+                    }
+                    return Poll::Ready(None);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for PingStream {
+    fn drop(&mut self) {
+        if let Some(child) = self.child.take() {
+            terminate_child(child);
+        }
+    }
+}
+
+// Hops traceroute gives up after when the call doesn't say
+const DEFAULT_MAX_HOPS: u32 = 30;
+// The TTL field's limit
+const MAX_HOPS: u32 = 255;
+
+// Trace the route to `address` hop by hop, numerically so the trace isn't
+// slowed down by reverse lookups
+fn build_traceroute_command(address: &str, max_hops: u32, flavor: PingFlavor) -> Command {
+    let address = normalize_address(address);
+    let mut cmd = if flavor == PingFlavor::Windows {
+        let mut c = Command::new("tracert");
+        c.arg("-d").args(["-h", &max_hops.to_string()]);
+        c
+    } else {
+        // Like ping, BSD traceroute is IPv4 only next to a `traceroute6`
+        let v6 = flavor == PingFlavor::Bsd && is_ipv6_literal(&address);
+        let mut c = Command::new(if v6 { "traceroute6" } else { "traceroute" });
+        c.arg("-n").args(["-m", &max_hops.to_string()]);
+        c
+    };
+    cmd.arg(&address);
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+    hide_window(&mut cmd);
+    cmd
+}
+
+// One hop of a running traceroute; `target` tells concurrent traces apart
+#[derive(Clone, Serialize)]
+struct TracerouteHopEvent {
+    target: String,
+    index: u32,
+    address: Option<String>,
+    rtt_ms: Option<f64>,
+}
+
+#[derive(Clone, Serialize)]
+struct TracerouteComplete {
+    target: String,
+    hops: u32,
+}
+
+// Run traceroute (tracert on Windows) to completion, emitting
+// `traceroute-hop` for each hop as it's printed and then `traceroute-complete`
+#[tauri::command]
+async fn traceroute(app: AppHandle, address: String, max_hops: Option<u32>) -> Result<(), String> {
+    validate_address(&address)?;
+    let max_hops = max_hops.unwrap_or(DEFAULT_MAX_HOPS);
+    if !(1..=MAX_HOPS).contains(&max_hops) {
+        return Err(format!("Max hops must be between 1 and {}", MAX_HOPS));
+    }
+
+    // A trace can take minutes, so it's waited on from the blocking pool
+    run_blocking(move || trace(&app, address, max_hops)).await
+}
+
+fn trace<R: Runtime>(app: &AppHandle<R>, address: String, max_hops: u32) -> Result<(), String> {
+    let state = app.state::<PingManager>();
+    let cmd = build_traceroute_command(&address, max_hops, state.ping_flavor(None));
+    let spawned = state.spawner.spawn(cmd).map_err(|e| {
+        if e.kind() == io::ErrorKind::NotFound {
+            "traceroute binary not found; install traceroute".to_string()
+        } else {
+            format!("Failed to spawn traceroute: {}", e)
+        }
+    })?;
+
+    // Drained alongside stdout, so a chatty stderr can't fill its pipe and
+    // stall traceroute before it gets to exit
+    let stderr = spawned.stderr.map(|stderr| {
+        thread::spawn(move || output_lines(stderr).map_while(Result::ok).collect::<Vec<_>>())
+    });
+
+    let mut hops = 0;
+    for line in output_lines(spawned.stdout).map_while(Result::ok) {
+        let Some(hop) = parse_traceroute_hop(&line) else {
+            continue;
+        };
+        hops += 1;
+        emit_event(app, EventName::TracerouteHop, TracerouteHopEvent {
+            target: address.clone(),
+            index: hop.index,
+            address: hop.address.map(|ip| ip.to_string()),
+            rtt_ms: hop.rtt_ms,
+        });
+    }
+
+    let errors: Vec<String> = stderr
+        .and_then(|reader| reader.join().ok())
+        .unwrap_or_default();
+    let success = match spawned.child {
+        Some(mut child) => child.wait().is_ok_and(|status| status.success()),
+        None => true,
+    };
+    if !success && hops == 0 {
+        let message = errors.join("\n");
+        return Err(if message.trim().is_empty() {
+            "traceroute failed".to_string()
+        } else {
+            message.trim().to_string()
+        });
+    }
+
+    emit_event(app, EventName::TracerouteComplete, TracerouteComplete {
+        target: address,
+        hops,
+    });
+    Ok(())
+}
+
+// Ping a host once and wait for the latency instead of streaming events
+#[tauri::command]
+async fn ping_once(app: AppHandle, address: String) -> Result<f64, String> {
+    validate_address(&address)?;
+
+    run_blocking(move || {
+        let state = app.state::<PingManager>();
+        ping_binary_once(
+            state.spawner.as_ref(),
+            &TogglePingArgs {
+                address,
+                count: Some(1),
+                ..Default::default()
+            },
+            state.ping_flavor(None),
+        )
+    })
+    .await
+}
+
+// Run ping to completion and return the first reply's latency
+fn ping_binary_once(
+    spawner: &dyn ProcessSpawner,
+    args: &TogglePingArgs,
+    flavor: PingFlavor,
+) -> Result<f64, String> {
+    let spawned = spawn_ping_process(spawner, args, flavor)?;
+
+    let time_ms = output_lines(spawned.stdout)
+        .map_while(Result::ok)
+        .find_map(|line| parse_ping_reply(&line))
+        .map(|reply| reply.latency.time_ms);
+    if let Some(mut child) = spawned.child {
+        let _ = child.wait();
+    }
+
+    time_ms.ok_or_else(|| "Ping timed out".to_string())
+}
+
+// List the servers with a running ping so the UI can re-sync after a reload
+#[tauri::command]
+fn active_pings(state: State<'_, PingManager>) -> Vec<String> {
+    let processes = state.processes.lock();
+    processes.keys().cloned().collect()
+}
+
+// Drop entries whose ping exited without its reader cleaning up, returning their ids
+#[tauri::command]
+fn reconcile(state: State<'_, PingManager>) -> Vec<String> {
+    let mut processes = state.processes.lock();
+    let mut exited = Vec::new();
+    for (server_id, process) in processes.iter_mut() {
+        if let Some(child) = process.child.as_mut() {
+            if let Ok(Some(_)) = child.try_wait() {
+                exited.push(server_id.clone());
+            }
+        }
+    }
+
+    for server_id in &exited {
+        processes.remove(server_id);
+    }
+    exited
+}
+
+// Quote a label value for the Prometheus text format
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn gauge_header(name: &str, help: &str) -> String {
+    format!("# HELP {} {}\n# TYPE {} gauge\n", name, help, name)
+}
+
+// Current per-server gauges in Prometheus exposition format, for scraping
+#[tauri::command]
+fn prometheus_metrics(state: State<'_, PingManager>) -> String {
+    let runs: Vec<(String, Arc<RunState>)> = state
+        .processes
+        .lock()
+        .iter()
+        .map(|(server_id, process)| (server_id.clone(), process.run.clone()))
+        .collect();
+
+    let mut latency = gauge_header("ping_latency_ms", "Latency of the most recent reply.");
+    let mut loss = gauge_header("ping_loss_percent", "Share of echoes lost so far this run.");
+    let mut up = gauge_header("ping_up", "Whether the most recent echo was answered.");
+
+    for (server_id, run) in runs {
+        let label = format!("{{server_id=\"{}\"}}", escape_label(&server_id));
+        let last = run.last.lock();
+        if let Some(time_ms) = last.time_ms {
+            latency.push_str(&format!("ping_latency_ms{} {}\n", label, time_ms));
+        }
+        let echoes = last.replies + last.failures;
+        if echoes > 0 {
+            let loss_percent = f64::from(last.failures) * 100.0 / f64::from(echoes);
+            loss.push_str(&format!("ping_loss_percent{} {}\n", label, loss_percent));
+        }
+        if let Some(is_up) = last.up {
+            up.push_str(&format!("ping_up{} {}\n", label, u8::from(is_up)));
+        }
+    }
+
+    latency + &loss + &up
+}
+
+// Summarize every running server for debugging
+#[tauri::command]
+fn manager_snapshot(state: State<'_, PingManager>) -> Vec<ServerSnapshot> {
+    let processes = state.processes.lock();
+    let samples = state.samples.lock();
+    processes
+        .iter()
+        .map(|(server_id, process)| ServerSnapshot {
+            server_id: server_id.clone(),
+            sample_count: samples.get(server_id).map_or(0, VecDeque::len),
+            paused: *process.run.paused.lock(),
+            uptime_ms: process.started_at.elapsed().as_millis() as u64,
+        })
+        .collect()
+}
+
+// Fraction of a running server's echoes that were answered, for a live
+// "99.2% up"; kept as two counters, so it's cheap to poll
+#[tauri::command]
+fn availability(state: State<'_, PingManager>, server_id: String) -> Option<f64> {
+    let run = state.processes.lock().get(&server_id)?.run.clone();
+    let availability = run.last.lock().availability();
+    availability
+}
+
+#[derive(Clone, Copy, Deserialize)]
+enum SortKey {
+    Latency,
+    Loss,
+    Jitter,
+}
+
+// One server's place on the leaderboard, from its run so far
+#[derive(Clone, Serialize)]
+struct LeaderboardEntry {
+    server_id: String,
+    avg_ms: Option<f64>,
+    jitter_ms: Option<f64>,
+    loss_percent: Option<f64>,
+}
+
+// The `limit` running servers doing worst by `by`, worst first; servers
+// with nothing to rank by yet come last
+#[tauri::command]
+fn leaderboard(state: State<'_, PingManager>, by: SortKey, limit: usize) -> Vec<LeaderboardEntry> {
+    let runs: Vec<(String, Arc<RunState>)> = state
+        .processes
+        .lock()
+        .iter()
+        .map(|(server_id, process)| (server_id.clone(), process.run.clone()))
+        .collect();
+
+    let mut entries: Vec<LeaderboardEntry> = runs
+        .into_iter()
+        .map(|(server_id, run)| {
+            let stats = run.latency.lock().summary(&server_id);
+            let availability = run.last.lock().availability();
+            LeaderboardEntry {
+                server_id,
+                avg_ms: stats.avg_ms,
+                jitter_ms: stats.jitter_ms,
+                loss_percent: availability.map(|availability| (1.0 - availability) * 100.0),
+            }
+        })
+        .collect();
+
+    let key = |entry: &LeaderboardEntry| match by {
+        SortKey::Latency => entry.avg_ms,
+        SortKey::Loss => entry.loss_percent,
+        SortKey::Jitter => entry.jitter_ms,
+    };
+    entries.sort_by(|a, b| match (key(a), key(b)) {
+        (Some(a), Some(b)) => b.total_cmp(&a),
+        (a, b) => b.is_some().cmp(&a.is_some()),
+    });
+    entries.truncate(limit);
+    entries
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_store::Builder::new().build())
+        .manage(PingManager::new(DEFAULT_MAX_CONCURRENT))
+        .manage(Mutex::new(PingConfig::default()))
+        .invoke_handler(tauri::generate_handler![
+            toggle_ping,
+            stop_all_pings,
+            stop_ping,
+            active_pings,
+            ping_once,
+            export_csv,
+            export_json,
+            recent_samples,
+            set_ping_config,
+            pause_ping,
+            resume_ping,
+            set_target_count,
+            reconcile,
+            manager_snapshot,
+            prometheus_metrics,
+            preview_command,
+            availability,
+            stop_group,
+            clear_history,
+            clear_all_history,
+            set_server_meta,
+            soak_test,
+            restart_ping,
+            leaderboard,
+            set_emission,
+            traceroute
+        ])
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Don't leave orphaned ping processes behind when the app exits
+            if let RunEvent::Exit = event {
+                app.state::<PingManager>().shutdown();
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::parse_ping_line;
+    use std::io::Cursor;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use tauri::test::{mock_builder, mock_context, noop_assets, MockRuntime};
+    use tauri::{App, Listener};
+    use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+    use tokio_stream::StreamExt;
+
+    // Hands out canned output in place of running ping
+    struct FakeSpawner {
+        output: Box<dyn Fn() -> OutputReader + Send + Sync>,
+        // Everything spawned so far, program first
+        commands: Arc<Mutex<Vec<Vec<String>>>>,
+        ping_installed: bool,
+    }
+
+    impl FakeSpawner {
+        fn new(output: impl Fn() -> OutputReader + Send + Sync + 'static) -> Self {
+            Self {
+                output: Box::new(output),
+                commands: Arc::default(),
+                ping_installed: true,
+            }
+        }
+
+        // As if the system had no ping, though any other program is there
+        fn without_ping(self) -> Self {
+            Self {
+                ping_installed: false,
+                ..self
+            }
+        }
+
+        // Every spawn prints `text` and exits
+        fn replaying(text: &'static str) -> Self {
+            Self::new(move || Box::new(Cursor::new(text)))
+        }
+
+        // One spawn whose output is sent as the test goes, ending once the
+        // sender is dropped
+        fn live() -> (UnboundedSender<Vec<u8>>, Self) {
+            let (tx, rx) = unbounded_channel();
+            let rx = Mutex::new(Some(rx));
+            let spawner = Self::new(move || {
+                let chunks = rx.lock().take().expect("spawned more than once");
+                Box::new(BufReader::new(ChannelReader {
+                    chunks,
+                    pending: Cursor::default(),
+                }))
+            });
+            (tx, spawner)
+        }
+
+        // Every spawn is a ping that never ends, printing a reply every few
+        // milliseconds until its output is dropped
+        fn endless() -> Self {
+            Self::new(|| {
+                let (tx, chunks) = unbounded_channel();
+                thread::spawn(move || {
+                    for seq in 1.. {
+                        thread::sleep(Duration::from_millis(5));
+                        let line = format!(
+                            "64 bytes from 127.0.0.1: icmp_seq={seq} ttl=64 time=0.041 ms\n"
+                        );
+                        if tx.send(line.into_bytes()).is_err() {
+                            break;
+                        }
+                    }
+                });
+                Box::new(BufReader::new(ChannelReader {
+                    chunks,
+                    pending: Cursor::default(),
+                }))
+            })
+        }
+    }
+
+    impl ProcessSpawner for FakeSpawner {
+        fn spawn(&self, cmd: Command) -> io::Result<Spawned> {
+            let line = std::iter::once(cmd.get_program()).chain(cmd.get_args());
+            let line = line.map(|arg| arg.to_string_lossy().into_owned()).collect();
+            self.commands.lock().push(line);
+            Ok(Spawned {
+                child: None,
+                stdout: (self.output)(),
+                stderr: None,
+            })
+        }
+
+        // Nothing is actually run, so only what the test says is missing
+        fn can_run(&self, program: &Path) -> bool {
+            self.ping_installed || program != Path::new("ping")
+        }
+    }
+
+    // Reads whatever the test sent, waiting like a pipe until more arrives
+    struct ChannelReader {
+        chunks: tokio::sync::mpsc::UnboundedReceiver<Vec<u8>>,
+        pending: Cursor<Vec<u8>>,
+    }
+
+    impl AsyncRead for ChannelReader {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            while self.pending.position() as usize == self.pending.get_ref().len() {
+                match ready!(self.chunks.poll_recv(cx)) {
+                    Some(chunk) => self.pending = Cursor::new(chunk),
+                    None => return Poll::Ready(Ok(())),
+                }
+            }
+            Pin::new(&mut self.pending).poll_read(cx, buf)
+        }
+    }
+
+    const RECORDED_EVENTS: [EventName; 15] = [
+        EventName::Started,
+        EventName::Stopped,
+        EventName::DnsResolved,
+        EventName::Header,
+        EventName::Result,
+        EventName::Failure,
+        EventName::Timeout,
+        EventName::Duplicate,
+        EventName::ReorderCount,
+        EventName::PacketLoss,
+        EventName::RttSummary,
+        EventName::Stats,
+        EventName::Progress,
+        EventName::Error,
+        EventName::Complete,
+    ];
+
+    // An app managing what `run` sets up, with ping replaced by `spawner`
+    fn test_app(spawner: FakeSpawner) -> App<MockRuntime> {
+        managing(PingManager::default(), spawner)
+    }
+
+    fn managing(manager: PingManager, spawner: FakeSpawner) -> App<MockRuntime> {
+        let manager = manager.with_spawner(Arc::new(spawner));
+        // Detecting it would run the real ping
+        manager.flavors.lock().insert(PathBuf::from("ping"), platform_flavor());
+        mock_builder()
+            .plugin(tauri_plugin_store::Builder::new().build())
+            .manage(manager)
+            .manage(Mutex::new(PingConfig::default()))
+            .build(mock_context(noop_assets()))
+            .unwrap()
+    }
+
+    // The names of the app's events in the order they were sent
+    fn record_events(app: &App<MockRuntime>) -> mpsc::Receiver<&'static str> {
+        let (tx, rx) = mpsc::channel();
+        for event in RECORDED_EVENTS {
+            let tx = tx.clone();
+            app.listen_any(event.as_str(), move |_| {
+                let _ = tx.send(event.as_str());
+            });
+        }
+        rx
+    }
+
+    // Events up to the next one named `last`, which is included
+    fn events_until(events: &mpsc::Receiver<&'static str>, last: EventName) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        loop {
+            let name = events
+                .recv_timeout(Duration::from_secs(10))
+                .unwrap_or_else(|_| panic!("no {} after {names:?}", last.as_str()));
+            names.push(name);
+            if name == last.as_str() {
+                return names;
+            }
+        }
+    }
+
+    fn count(names: &[&str], event: EventName) -> usize {
+        names.iter().filter(|name| **name == event.as_str()).count()
+    }
+
+    fn send_line(output: &UnboundedSender<Vec<u8>>, line: &str) {
+        output.send(format!("{line}\n").into_bytes()).unwrap();
+    }
+
+    fn ping_args(server_id: &str, count: Option<u32>) -> TogglePingArgs {
+        TogglePingArgs {
+            server_id: server_id.to_string(),
+            address: "127.0.0.1".to_string(),
+            count,
+            ..Default::default()
+        }
+    }
+
+    fn start(app: &App<MockRuntime>, args: TogglePingArgs) {
+        let outcome = toggle(app.handle(), args);
+        assert!(matches!(outcome, Ok(ToggleOutcome::Toggled(true))), "didn't start");
+    }
+
+    // (seq, time_ms) of each echo the server's latest run recorded
+    fn recorded(app: &App<MockRuntime>, server_id: &str) -> Vec<(Option<u32>, Option<f64>)> {
+        let state = app.state::<PingManager>();
+        let samples = state.samples.lock();
+        samples[server_id].iter().map(|sample| (sample.seq, sample.time_ms)).collect()
+    }
+
+    const LINUX_RUN: &str = "\
+PING 127.0.0.1 (127.0.0.1) 56(84) bytes of data.
+64 bytes from 127.0.0.1: icmp_seq=1 ttl=64 time=0.041 ms
+64 bytes from 127.0.0.1: icmp_seq=2 ttl=64 time=0.052 ms
+no answer yet for icmp_seq=3
+64 bytes from 127.0.0.1: icmp_seq=4 ttl=64 time=0.047 ms
+
+--- 127.0.0.1 ping statistics ---
+4 packets transmitted, 3 received, 25% packet loss, time 3004ms
+rtt min/avg/max/mdev = 0.041/0.046/0.052/0.004 ms
+";
+
+    #[test]
+    fn reports_canned_output_as_events() {
+        let app = test_app(FakeSpawner::replaying(LINUX_RUN));
+        let events = record_events(&app);
+        start(&app, ping_args("local", Some(4)));
+
+        let names = events_until(&events, EventName::Complete);
+        assert_eq!(names[0], EventName::Started.as_str());
+        assert_eq!(count(&names, EventName::Header), 1);
+        assert_eq!(count(&names, EventName::Result), 3);
+        assert_eq!(count(&names, EventName::Failure), 1);
+        assert_eq!(count(&names, EventName::Timeout), 1);
+        assert_eq!(count(&names, EventName::PacketLoss), 1);
+        assert_eq!(count(&names, EventName::RttSummary), 1);
+        assert_eq!(count(&names, EventName::Stats), 1);
+        assert_eq!(count(&names, EventName::Stopped), 0);
+        assert_eq!(count(&names, EventName::Error), 0);
+
+        assert_eq!(
+            recorded(&app, "local"),
+            [
+                (Some(1), Some(0.041)),
+                (Some(2), Some(0.052)),
+                (Some(3), None),
+                (Some(4), Some(0.047)),
+            ]
+        );
+        assert!(app.state::<PingManager>().processes.lock().is_empty());
+    }
+
+    // Fails every read, like a pipe that broke
+    struct BrokenPipe;
+
+    impl AsyncRead for BrokenPipe {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            _buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            Poll::Ready(Err(io::Error::new(io::ErrorKind::BrokenPipe, "pipe broke")))
+        }
+    }
+
+    const WINDOWS_ALL_LOST: &str = "\
+Pinging 127.0.0.1 with 32 bytes of data:\r
+Request timed out.\r
+Request timed out.\r
+Request timed out.\r
+Request timed out.\r
+\r
+Ping statistics for 127.0.0.1:\r
+    Packets: Sent = 4, Received = 0, Lost = 4 (100% loss),\r
+";
+
+    #[test]
+    fn counts_a_fully_lost_run_once_per_echo() {
+        let app = test_app(FakeSpawner::replaying(WINDOWS_ALL_LOST));
+        let events = record_events(&app);
+        start(&app, ping_args("local", Some(4)));
+
+        // The summary is reported as the run's loss, not as a fifth timeout
+        let names = events_until(&events, EventName::Complete);
+        assert_eq!(count(&names, EventName::Failure), 4);
+        assert_eq!(count(&names, EventName::Timeout), 4);
+        assert_eq!(count(&names, EventName::PacketLoss), 1);
+        let lost: Vec<_> = (1..=4).map(|seq| (Some(seq), None)).collect();
+        assert_eq!(recorded(&app, "local"), lost);
+    }
+
+    // A count of zero, for toggle_ping to only check the address
+    fn check_args(server_id: &str) -> TogglePingArgs {
+        ping_args(server_id, Some(0))
+    }
+
+    #[test]
+    fn checks_connectivity_with_the_fallback_a_run_would_use() {
+        let spawner = FakeSpawner::replaying(LINUX_RUN).without_ping();
+        let commands = spawner.commands.clone();
+        let app = test_app(spawner);
+        // Any executable will do, since nothing is actually run
+        let bundled = std::env::current_exe().unwrap();
+        let state = app.state::<PingManager>();
+        state.flavors.lock().insert(bundled.clone(), platform_flavor());
+
+        let args = TogglePingArgs {
+            fallback: Some(PingFallback::Binary(bundled.clone())),
+            ..check_args("local")
+        };
+        let outcome = toggle(app.handle(), args);
+        assert!(matches!(outcome, Ok(ToggleOutcome::Checked(ref check)) if check.time_ms == 0.041));
+        let bundled = bundled.display().to_string();
+        assert!(commands.lock().iter().all(|line| line.contains(&bundled)), "{commands:?}");
+        assert_eq!(commands.lock().len(), 1);
+
+        // Native checks, asked for or fallen back on, don't run ping at all
+        let native = [
+            TogglePingArgs {
+                use_native: true,
+                ..check_args("local")
+            },
+            TogglePingArgs {
+                fallback: Some(PingFallback::Native),
+                ..check_args("local")
+            },
+        ];
+        for args in native {
+            let _ = toggle(app.handle(), args);
+        }
+        assert_eq!(commands.lock().len(), 1);
+        assert!(state.processes.lock().is_empty());
+    }
+
+    #[test]
+    fn ends_the_run_when_output_fails_partway() {
+        let app = test_app(FakeSpawner::new(|| {
+            let output = "\
+PING 127.0.0.1 (127.0.0.1) 56(84) bytes of data.
+64 bytes from 127.0.0.1: icmp_seq=1 ttl=64 time=0.041 ms
+64 bytes from 127.0.0.1: icmp_seq=2 ttl=64 time=0.052 ms
+";
+            Box::new(BufReader::new(Cursor::new(output).chain(BrokenPipe)))
+        }));
+        let events = record_events(&app);
+        start(&app, ping_args("local", Some(5)));
+
+        let names = events_until(&events, EventName::Complete);
+        assert_eq!(count(&names, EventName::Result), 2);
+        assert_eq!(count(&names, EventName::Error), 1);
+        // Reported before the run wraps up, not lost along with the pipe
+        let error_at = names.iter().position(|name| *name == EventName::Error.as_str());
+        let stats_at = names.iter().position(|name| *name == EventName::Stats.as_str());
+        assert!(error_at < stats_at, "{names:?}");
+        assert_eq!(recorded(&app, "local"), [(Some(1), Some(0.041)), (Some(2), Some(0.052))]);
+        assert!(app.state::<PingManager>().processes.lock().is_empty());
+    }
+
+    #[test]
+    fn reports_each_reply_while_ping_keeps_running() {
+        let (output, spawner) = FakeSpawner::live();
+        let app = test_app(spawner);
+        let events = record_events(&app);
+        start(&app, ping_args("local", None));
+
+        // Each reply comes out before ping prints the next, let alone exits
+        for seq in 1..=3 {
+            send_line(
+                &output,
+                &format!("64 bytes from 127.0.0.1: icmp_seq={seq} ttl=64 time=0.041 ms"),
+            );
+            events_until(&events, EventName::Result);
+            assert_eq!(recorded(&app, "local").len(), seq);
+        }
+        assert!(app.state::<PingManager>().processes.lock().contains_key("local"));
+
+        // Ping exiting on its own, which for continuous mode it only does on error
+        drop(output);
+        let names = events_until(&events, EventName::Complete);
+        assert_eq!(count(&names, EventName::Result), 0);
+    }
+
+    #[test]
+    fn counts_replies_that_arrive_out_of_order() {
+        let app = test_app(FakeSpawner::replaying(
+            "\
+64 bytes from 127.0.0.1: icmp_seq=1 ttl=64 time=0.041 ms
+64 bytes from 127.0.0.1: icmp_seq=3 ttl=64 time=0.041 ms
+64 bytes from 127.0.0.1: icmp_seq=2 ttl=64 time=0.041 ms
+64 bytes from 127.0.0.1: icmp_seq=4 ttl=64 time=0.041 ms
+64 bytes from 127.0.0.1: icmp_seq=5 ttl=64 time=0.041 ms
+64 bytes from 127.0.0.1: icmp_seq=4 ttl=64 time=0.041 ms (DUP!)
+",
+        ));
+        let events = record_events(&app);
+        start(&app, ping_args("local", None));
+
+        let names = events_until(&events, EventName::Complete);
+        assert_eq!(count(&names, EventName::Result), 5);
+        assert_eq!(count(&names, EventName::Duplicate), 1);
+        // Only seq 2, coming after 3; the duplicate isn't another arrival
+        assert_eq!(count(&names, EventName::ReorderCount), 1);
+    }
+
+    #[test]
+    fn keeps_order_across_sequence_wraparound() {
+        let app = test_app(FakeSpawner::replaying(
+            "\
+64 bytes from 127.0.0.1: icmp_seq=65534 ttl=64 time=0.041 ms
+64 bytes from 127.0.0.1: icmp_seq=65535 ttl=64 time=0.041 ms
+64 bytes from 127.0.0.1: icmp_seq=0 ttl=64 time=0.041 ms
+64 bytes from 127.0.0.1: icmp_seq=1 ttl=64 time=0.041 ms
+",
+        ));
+        let events = record_events(&app);
+        start(&app, ping_args("local", None));
+
+        let names = events_until(&events, EventName::Complete);
+        assert_eq!(count(&names, EventName::Result), 4);
+        assert_eq!(count(&names, EventName::ReorderCount), 0);
+    }
+
+    #[test]
+    fn compares_sequence_numbers_as_serial_numbers() {
+        assert!(seq_precedes(1, 2));
+        assert!(!seq_precedes(2, 1));
+        assert!(!seq_precedes(7, 7));
+        // After wrapping, 0 is newer than 65535 and 65535 older than 0
+        assert!(seq_precedes(65535, 0));
+        assert!(!seq_precedes(0, 65535));
+        assert!(seq_precedes(65000, 100));
+        assert!(!seq_precedes(100, 65000));
+    }
+
+    #[test]
+    fn stops_a_server_even_with_a_garbage_address() {
+        let (_output, spawner) = FakeSpawner::live();
+        let app = test_app(spawner);
+        start(&app, ping_args("local", None));
+
+        let garbage = TogglePingArgs {
+            address: "not a host;%%".to_string(),
+            ..ping_args("local", None)
+        };
+        let outcome = toggle(app.handle(), garbage.clone());
+        assert!(matches!(outcome, Ok(ToggleOutcome::Toggled(false))));
+        assert!(app.state::<PingManager>().processes.lock().is_empty());
+
+        // With nothing left to stop it would have to start, which it can't
+        assert!(toggle(app.handle(), garbage).is_err());
+        assert!(app.state::<PingManager>().processes.lock().is_empty());
+    }
+
+    fn drain(events: &mpsc::Receiver<&'static str>) -> Vec<&'static str> {
+        events.try_iter().collect()
+    }
+
+    #[test]
+    fn concurrent_toggles_leave_at_most_one_run() {
+        let app = test_app(FakeSpawner::endless());
+        let events = record_events(&app);
+        // Shared by every thread, the way concurrent commands share it
+        let handle = app.handle();
+
+        let outcomes: Vec<bool> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..16)
+                .map(|_| {
+                    scope.spawn(|| {
+                        (0..25)
+                            .map(|_| match toggle(handle, ping_args("local", None)) {
+                                Ok(ToggleOutcome::Toggled(started)) => started,
+                                _ => panic!("toggle failed"),
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect()
+        });
+        let started = outcomes.iter().filter(|started| **started).count();
+        let stopped = outcomes.len() - started;
+        let running = app.state::<PingManager>().processes.lock().len();
+        assert_eq!(started - stopped, running);
+
+        if running == 1 {
+            let outcome = toggle(app.handle(), ping_args("local", None));
+            assert!(matches!(outcome, Ok(ToggleOutcome::Toggled(false))));
+        }
+        thread::sleep(STOP_GRACE_PERIOD);
+        let names = drain(&events);
+        assert_eq!(count(&names, EventName::Started), started);
+        assert_eq!(count(&names, EventName::Stopped), stopped + running);
+
+        // Every run was stopped, so nothing is left reporting replies
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(count(&drain(&events), EventName::Result), 0);
+    }
+
+    #[test]
+    fn toggling_a_running_server_stops_it() {
+        let (output, spawner) = FakeSpawner::live();
+        let app = test_app(spawner);
+        let events = record_events(&app);
+        start(&app, ping_args("local", None));
+        send_line(&output, "64 bytes from 127.0.0.1: icmp_seq=1 ttl=64 time=0.041 ms");
+        events_until(&events, EventName::Result);
+
+        let outcome = toggle(app.handle(), ping_args("local", None));
+        assert!(matches!(outcome, Ok(ToggleOutcome::Toggled(false))));
+        events_until(&events, EventName::Stopped);
+        assert!(app.state::<PingManager>().processes.lock().is_empty());
+    }
+
+    #[test]
+    fn stops_a_server_whose_restart_fails() {
+        let app = test_app(FakeSpawner::endless());
+        let events = record_events(&app);
+        start(&app, ping_args("local", None));
+        events_until(&events, EventName::Result);
+
+        // Started over with these, the loopback address is turned away
+        let state = app.state::<PingManager>();
+        let previous = state.sessions.lock()["local"].clone();
+        state.sessions.lock().insert("local".to_string(), Arc::new(Session {
+            started_at_ms: previous.started_at_ms,
+            ended_at_ms: Mutex::default(),
+            args: TogglePingArgs { reject_special: true, ..previous.args.clone() },
+            command_line: previous.command_line.clone(),
+        }));
+        assert!(restart(app.handle(), "local".to_string()).is_err());
+        events_until(&events, EventName::Stopped);
+        assert!(state.processes.lock().is_empty());
+    }
+
+    #[test]
+    fn forgetting_a_running_server_starts_its_totals_over() {
+        let (output, spawner) = FakeSpawner::live();
+        let app = test_app(spawner);
+        let events = record_events(&app);
+        start(&app, ping_args("local", None));
+        send_line(&output, "64 bytes from 127.0.0.1: icmp_seq=1 ttl=64 time=0.041 ms");
+        send_line(&output, "64 bytes from 127.0.0.1: icmp_seq=2 ttl=64 time=0.052 ms");
+        events_until(&events, EventName::Result);
+        events_until(&events, EventName::Result);
+
+        let state = app.state::<PingManager>();
+        forget_last_run(&state, "local");
+        send_line(&output, "64 bytes from 127.0.0.1: icmp_seq=3 ttl=64 time=0.047 ms");
+        events_until(&events, EventName::Result);
+
+        let run = state.processes.lock()["local"].run.clone();
+        assert_eq!(run.last.lock().replies, 1);
+        assert_eq!(run.latency.lock().samples, 1);
+        assert_eq!(recorded(&app, "local"), [(Some(3), Some(0.047))]);
+    }
+
+    #[test]
+    fn restarts_a_paused_server_still_paused() {
+        let app = test_app(FakeSpawner::endless());
+        start(&app, ping_args("local", None));
+        let state = app.state::<PingManager>();
+        assert!(set_paused(&state, "local", true));
+        *state.processes.lock()["local"].run.target_count.lock() = Some(50);
+
+        restart(app.handle(), "local".to_string()).unwrap();
+        let processes = state.processes.lock();
+        assert!(*processes["local"].run.paused.lock());
+        assert_eq!(*processes["local"].run.target_count.lock(), Some(50));
+    }
+
+    #[test]
+    fn ends_a_probe_run_without_waiting_out_its_interval() {
+        let app = test_app(FakeSpawner::replaying(""));
+        let events = record_events(&app);
+        // Refused right away, leaving only the interval to wait out
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let hourly = TogglePingArgs {
+            port: Some(port),
+            interval_secs: Some(3600.0),
+            deadline_secs: Some(1),
+            ..ping_args("local", None)
+        };
+
+        let started = Instant::now();
+        start(&app, hourly.clone());
+        events_until(&events, EventName::Complete);
+        assert!(started.elapsed() < Duration::from_secs(3));
+
+        start(&app, TogglePingArgs { deadline_secs: None, ..hourly.clone() });
+        events_until(&events, EventName::Failure);
+        let run = app.state::<PingManager>().processes.lock()["local"].run.clone();
+        assert!(matches!(toggle(app.handle(), hourly), Ok(ToggleOutcome::Toggled(false))));
+        // The probe thread lets go of the run once it notices the stop
+        thread::sleep(STOP_GRACE_PERIOD);
+        assert_eq!(Arc::strong_count(&run), 1);
+    }
+
+    fn sized_args(packet_size: u32) -> TogglePingArgs {
+        TogglePingArgs {
+            address: "8.8.8.8".to_string(),
+            packet_size: Some(packet_size),
+            ..Default::default()
+        }
+    }
+
+    fn has_flag(line: &[String], flag: &str, value: &str) -> bool {
+        line.windows(2).any(|pair| pair[0] == flag && pair[1] == value)
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn sets_packet_size_with_dash_s() {
+        for flavor in [PingFlavor::Iputils, PingFlavor::Bsd, PingFlavor::Busybox] {
+            let line = ping_command_line(&sized_args(1400), flavor);
+            assert!(has_flag(&line, "-s", "1400"), "{line:?}");
+            assert!(!line.iter().any(|arg| arg == "-l"), "{line:?}");
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn sets_packet_size_with_dash_l() {
+        let line = ping_command_line(&sized_args(1400), PingFlavor::Windows);
+        assert!(has_flag(&line, "-l", "1400"), "{line:?}");
+        assert!(!line.iter().any(|arg| arg == "-s"), "{line:?}");
+    }
+
+    #[test]
+    fn accepts_ipv6_literals() {
+        let cases = [
+            ("::1", "::1"),
+            ("fe80::1%25eth0", "fe80::1%eth0"),
+            ("fe80::1%eth0", "fe80::1%eth0"),
+            ("[2001:db8::1]", "2001:db8::1"),
+            ("[fe80::1%25eth0]", "fe80::1%eth0"),
+        ];
+        for (address, normalized) in cases {
+            assert_eq!(normalize_address(address), normalized);
+            assert!(validate_address(address).is_ok(), "{address}");
+            assert!(is_ipv6_literal(&normalize_address(address)), "{address}");
+        }
+        for address in ["fe80::1%", "fe80::1%eth 0", "[2001:db8::1", "2001:db8::g", "::1%25"] {
+            assert!(validate_address(address).is_err(), "{address}");
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn pings_ipv6_literals_without_brackets() {
+        let args = TogglePingArgs {
+            address: "[fe80::1%25eth0]".to_string(),
+            ..Default::default()
+        };
+        let line = ping_command_line(&args, PingFlavor::Iputils);
+        assert_eq!(line.last().map(String::as_str), Some("fe80::1%eth0"));
+        assert!(line.iter().any(|arg| arg == "-6"), "{line:?}");
+    }
+
+    #[test]
+    fn detects_the_ping_from_its_version() {
+        let cases = [
+            ("ping from iputils 20240117", PingFlavor::Iputils),
+            ("ping utility, iputils-s20161105", PingFlavor::Iputils),
+            ("ping (GNU inetutils) 2.5", PingFlavor::Inetutils),
+            ("BusyBox v1.36.1 (2023-11-07 18:53:09 UTC) multi-call binary.", PingFlavor::Busybox),
+            ("", platform_flavor()),
+        ];
+        for (text, flavor) in cases {
+            assert!(flavor_from_version(text) == flavor, "{text}");
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn leaves_out_what_inetutils_lacks() {
+        let args = TogglePingArgs {
+            address: "2001:db8::1".to_string(),
+            count: Some(4),
+            timeout_ms: Some(1500),
+            ..Default::default()
+        };
+        let line = ping_command_line(&args, PingFlavor::Inetutils);
+        assert!(line.iter().any(|arg| arg == "ping6"), "{line:?}");
+        assert!(has_flag(&line, "-W", "2"), "{line:?}");
+        for flag in ["-O", "-4", "-6", "-M"] {
+            assert!(!line.iter().any(|arg| arg == flag), "{line:?}");
+        }
+
+        let unsupported = [
+            TogglePingArgs {
+                dont_fragment: true,
+                ..args.clone()
+            },
+            TogglePingArgs {
+                source: Some("eth0".to_string()),
+                ..args.clone()
+            },
+        ];
+        for args in unsupported {
+            assert!(validate_flavor(&args, PingFlavor::Inetutils).is_err());
+            assert!(validate_flavor(&args, PingFlavor::Iputils).is_ok());
+        }
+    }
+
+    #[test]
+    fn caps_the_count_at_the_maximum() {
+        assert!(validate_count(Some(DEFAULT_MAX_COUNT), DEFAULT_MAX_COUNT).is_ok());
+        assert!(validate_count(Some(DEFAULT_MAX_COUNT + 1), DEFAULT_MAX_COUNT).is_err());
+        assert!(validate_count(Some(10), 10).is_ok());
+        assert!(validate_count(Some(11), 10).is_err());
+        // Continuous runs have no count to cap
+        assert!(validate_count(None, 10).is_ok());
+    }
+
+    #[test]
+    fn floors_the_interval_of_the_other_backends() {
+        let backends = [
+            TogglePingArgs { use_native: true, ..ping_args("local", None) },
+            TogglePingArgs { port: Some(443), ..ping_args("local", None) },
+            TogglePingArgs {
+                mode: ProbeMode::Http,
+                address: "http://127.0.0.1/".to_string(),
+                ..ping_args("local", None)
+            },
+        ];
+        for args in backends {
+            let fast = TogglePingArgs { interval_secs: Some(0.0001), ..args.clone() };
+            assert!(validate_args(&fast).is_err());
+            let floor = TogglePingArgs { interval_secs: Some(MIN_INTERVAL_SECS), ..args };
+            assert!(validate_args(&floor).is_ok());
+        }
+    }
+
+    #[test]
+    fn reads_past_lines_that_arent_utf8() {
+        // "Zeitüberschreitung" in cp850, then a plain reply
+        let output = b"Zeit\x81berschreitung der Anforderung.\r\n\
+                       Antwort von 8.8.8.8: Bytes=32 Zeit=14ms TTL=117\r\n";
+        let lines: Vec<String> = output_lines(Box::new(Cursor::new(&output[..])))
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("berschreitung der Anforderung."), "{:?}", lines[0]);
+        assert_eq!(parse_ping_line(&lines[1]).map(|latency| latency.time_ms), Some(14.0));
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn decodes_oem_and_ansi_codepages() {
+        let cases: [(&[u8], u32, &str); 4] = [
+            (
+                b"Zeit\x81berschreitung der Anforderung.",
+                850,
+                "Zeitüberschreitung der Anforderung.",
+            ),
+            (
+                b"Zeit\xfcberschreitung der Anforderung.",
+                1252,
+                "Zeitüberschreitung der Anforderung.",
+            ),
+            (
+                b"D\x82lai d'attente de la demande d\x82pass\x82.",
+                850,
+                "Délai d'attente de la demande dépassé.",
+            ),
+            (
+                b"D\xe9lai d'attente de la demande d\xe9pass\xe9.",
+                1252,
+                "Délai d'attente de la demande dépassé.",
+            ),
+        ];
+        for (bytes, code_page, text) in cases {
+            let decoded = decode_codepage(bytes, code_page);
+            assert_eq!(decoded, text);
+            assert!(parser::is_timeout_line(&decoded), "{decoded}");
+        }
+    }
+
+    #[test]
+    fn reports_duplicates_instead_of_replies() {
+        let updates =
+            parse_output_line("64 bytes from 224.0.0.1: icmp_seq=3 ttl=64 time=0.512 ms (DUP!)");
+        assert!(matches!(updates[..], [PingUpdate::Duplicate { seq: Some(3) }]));
+    }
+
+    fn streaming(spawned: Spawned) -> PingStream {
+        PingStream {
+            output: read_output(spawned.stdout, spawned.stderr),
+            pending: VecDeque::new(),
+            child: spawned.child,
+        }
+    }
+
+    #[test]
+    fn streams_what_a_run_would_report() {
+        let spawned = Spawned {
+            child: None,
+            stdout: Box::new(Cursor::new(LINUX_RUN)),
+            stderr: Some(Box::new(Cursor::new("ping: sendmsg: No buffer space available\n"))),
+        };
+        let mut stream = streaming(spawned);
+        let mut replies = 0;
+        let mut errors = 0;
+        let mut loss = None;
+        while let Some(update) = tauri::async_runtime::block_on(stream.next()) {
+            match update {
+                PingUpdate::Reply(_) => replies += 1,
+                PingUpdate::Error(_) => errors += 1,
+                PingUpdate::PacketLoss(loss_percent) => loss = Some(loss_percent),
+                _ => {}
+            }
+        }
+        assert_eq!((replies, errors, loss), (3, 1, Some(25.0)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dropping_a_stream_stops_a_silent_ping() {
+        let mut silent = Command::new("sleep");
+        silent.arg("30").stdout(Stdio::piped()).stderr(Stdio::piped());
+        let spawned = CommandSpawner.spawn(silent).unwrap();
+        let pid = spawned.child.as_ref().unwrap().id() as libc::pid_t;
+
+        drop(streaming(spawned));
+        thread::sleep(STOP_GRACE_PERIOD);
+        // SAFETY: signal 0 only checks whether the process still exists
+        assert_ne!(unsafe { libc::kill(pid, 0) }, 0);
+    }
+
+    // This process's thread count and resident memory in KiB
+    #[cfg(target_os = "linux")]
+    fn process_usage() -> (usize, usize) {
+        let status = std::fs::read_to_string("/proc/self/status").unwrap();
+        let field = |name: &str| -> usize {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .and_then(|value| value.split_whitespace().next())
+                .and_then(|value| value.parse().ok())
+                .unwrap()
+        };
+        (field("Threads:"), field("VmRSS:"))
+    }
+
+    // Compares what 200 silent servers cost now against the three threads
+    // each used to keep waiting on ping: the reader loop and one per pipe.
+    // Measures the whole process, so run it on its own with
+    // `cargo test two_hundred_servers -- --ignored --nocapture`
+    #[cfg(target_os = "linux")]
+    #[test]
+    #[ignore]
+    fn two_hundred_servers_share_the_runtime() {
+        const SERVERS: usize = 200;
+        let (threads_at_start, rss_at_start) = process_usage();
+
+        let (release, waiting) = mpsc::channel::<()>();
+        let waiting = Arc::new(Mutex::new(waiting));
+        let blocked: Vec<_> = (0..SERVERS * 3)
+            .map(|_| {
+                let waiting = waiting.clone();
+                thread::spawn(move || {
+                    let _ = waiting.lock().recv();
+                })
+            })
+            .collect();
+        thread::sleep(Duration::from_millis(500));
+        let (threads, rss) = process_usage();
+        let before = (threads - threads_at_start, rss.saturating_sub(rss_at_start));
+        drop(release);
+        for thread in blocked {
+            thread.join().unwrap();
+        }
+
+        // Every ping stays silent until its output is dropped with the senders
+        let outputs = Arc::new(Mutex::new(Vec::new()));
+        let spawned = outputs.clone();
+        let app = managing(
+            PingManager::new(SERVERS),
+            FakeSpawner::new(move || {
+                let (tx, chunks) = unbounded_channel();
+                spawned.lock().push(tx);
+                Box::new(BufReader::new(ChannelReader {
+                    chunks,
+                    pending: Cursor::default(),
+                }))
+            }),
+        );
+        let (threads_at_start, rss_at_start) = process_usage();
+        for server in 0..SERVERS {
+            start(&app, ping_args(&format!("server-{server}"), None));
+        }
+        thread::sleep(Duration::from_millis(500));
+        let (threads, rss) = process_usage();
+        let after = (threads - threads_at_start, rss.saturating_sub(rss_at_start));
+        for server in 0..SERVERS {
+            stop_server(app.handle(), &app.state::<PingManager>(), &format!("server-{server}"));
+        }
+        outputs.lock().clear();
+
+        let (threads, rss) = before;
+        println!("{SERVERS} servers, one thread per reader: {threads} threads, {rss} KiB");
+        let (threads, rss) = after;
+        println!("{SERVERS} servers as tasks: {threads} threads, {rss} KiB");
+        // What's left is the runtime's own worker threads
+        assert!(after.0 < SERVERS, "{after:?}");
+    }
 }
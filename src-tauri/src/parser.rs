@@ -0,0 +1,740 @@
+// Parsing of ping's text output, kept free of Tauri so it can be tested and
+// fuzzed on its own
+use regex::Regex;
+use serde::Serialize;
+use std::net::IpAddr;
+use std::sync::LazyLock;
+
+// Why an echo went unanswered
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    Timeout,
+    HostUnreachable,
+    NetUnreachable,
+    // Only reported by TCP and HTTP probes
+    ConnectionRefused,
+    // An HTTP probe got a response outside 2xx
+    HttpStatus,
+    // The address didn't resolve, so nothing was started
+    DnsFailure,
+    // The packet was too large for the path with do-not-fragment set
+    FragmentationNeeded,
+    // A router on the way dropped the echo when its TTL ran out
+    TtlExceeded,
+}
+
+// ICMP errors sent back by routers along the path rather than the target
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IcmpErrorKind {
+    // The echo's TTL ran out, a sign of a routing loop or too low a TTL
+    TtlExceeded,
+    // A gateway pointed at a better route, which usually means it is misconfigured
+    Redirect,
+}
+
+// A reply line broken into its parts; only the latency is always present
+pub struct ParsedReply {
+    pub latency: Latency,
+    pub ttl: Option<u32>,
+    pub seq: Option<u32>,
+    pub bytes: Option<u32>,
+    pub from_ip: Option<IpAddr>,
+}
+
+// A reply's latency as parsed or measured
+#[derive(Clone, Copy)]
+pub struct Latency {
+    pub time_ms: f64,
+    // "time<1ms" on Windows, reported as 1ms
+    pub below_one_ms: bool,
+}
+
+impl ParsedReply {
+    // A reply timed by one of the backends that don't run the ping binary
+    pub fn measured(time_ms: f64, seq: u32) -> Self {
+        Self {
+            latency: Latency {
+                time_ms,
+                below_one_ms: false,
+            },
+            ttl: None,
+            seq: Some(seq),
+            bytes: None,
+            from_ip: None,
+        }
+    }
+}
+
+// One line of traceroute or tracert output; a hop where every probe timed
+// out has neither an address nor a latency
+pub struct TracerouteHop {
+    pub index: u32,
+    pub address: Option<IpAddr>,
+    // Fastest of the hop's probes
+    pub rtt_ms: Option<f64>,
+}
+
+// Round-trip times as reported by the ping binary's summary line
+#[derive(Clone, Copy, Serialize)]
+pub struct RttSummary {
+    pub min_ms: f64,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+    // Linux "mdev" or macOS "stddev"; Windows doesn't report one
+    pub mdev_ms: Option<f64>,
+}
+
+// Compiled once on first use instead of for every line of output
+// Anchored on the `=`/`<` rather than the word before it, which is localized
+// ("time", "Zeit", "tiempo", "temps", ...); summary lines like
+// "Minimum = 1ms" don't match because of the space after `=`
+static RE_LATENCY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"([=<])(\d+(?:[.,]\d+)?)\s*ms\b").unwrap());
+// Whole-run loss in any language, e.g. "100% packet loss" or "(100% perdidos)"
+static RE_FULL_LOSS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b100(?:[.,]0+)?\s*%").unwrap());
+static RE_SEQ: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?:icmp_)?seq=(\d+)").unwrap());
+static RE_BYTES: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(?:(\d+)\s+bytes\b|bytes=(\d+))").unwrap());
+// Only the English "from"; localized Windows replies leave the address out
+static RE_FROM: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\bfrom\s+(?:[^\s(]+\s+\()?([0-9a-f:.]+?)\)?:?\s").unwrap()
+});
+static RE_TTL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)ttl=(\d+)").unwrap());
+static RE_PACKET_LOSS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\d+(?:\.\d+)?)%\s*(?:packet\s+)?loss").unwrap());
+// Localized Windows losses, "(25% perdidos)," or "(perte 25%),", told
+// apart by the percentage being all there is in the parentheses
+static RE_PACKET_LOSS_LOCALIZED: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\([^\d()%]*(\d+(?:[.,]\d+)?)\s*%[^\d()%]*\)").unwrap()
+});
+// "rtt min/avg/max/mdev = 0.1/0.2/0.3/0.04 ms" on Linux, "round-trip
+// min/avg/max/stddev = ..." on macOS and without the last field on BusyBox
+static RE_RTT_UNIX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"min/avg/max(?:/\w+)?\s*=\s*([\d.]+)/([\d.]+)/([\d.]+)(?:/([\d.]+))?\s*ms").unwrap()
+});
+// "Minimum = 1ms, Maximum = 3ms, Average = 2ms", matched on the values
+// alone since the labels are localized
+static RE_RTT_WINDOWS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"=\s*(\d+)ms,\s*[^=,]+=\s*(\d+)ms,\s*[^=,]+=\s*(\d+)ms").unwrap()
+});
+// " 1  192.168.1.1  0.512 ms  0.430 ms  0.401 ms" from traceroute -n and
+// "  1    <1 ms    <1 ms    <1 ms  192.168.1.1" from tracert -d; headers
+// and footers don't start with a hop number
+static RE_HOP: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*(\d+)\s+(.+)$").unwrap());
+static RE_HOP_RTT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<?(\d+(?:\.\d+)?)\s*ms\b").unwrap());
+// "PING host (ip) 56(84) bytes of data." on Linux, "PING host (ip): 56 data
+// bytes" on macOS and BusyBox; iputils nests the IPv6 form as "host(name (ip))"
+static RE_HEADER_UNIX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^PING\s+\S.*?\(([0-9A-Fa-f:.]+)\)\)?:?\s+(\d+)(?:\(\d+\))?\s+(?:data\s+)?bytes").unwrap()
+});
+// "Pinging host [ip] with 32 bytes of data:", without the brackets for an IP
+static RE_HEADER_WINDOWS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^Pinging\s+(\S+)\s+(?:\[([^\]\s]+)\]\s+)?with\s+(\d+)\s+bytes").unwrap()
+});
+
+// Parse the line ping starts with into the IP it resolved and the payload size
+pub fn parse_ping_header(line: &str) -> Option<(String, u32)> {
+    if let Some(caps) = RE_HEADER_UNIX.captures(line) {
+        return Some((caps.get(1)?.as_str().to_string(), caps.get(2)?.as_str().parse().ok()?));
+    }
+
+    let caps = RE_HEADER_WINDOWS.captures(line)?;
+    let ip = caps.get(2).or_else(|| caps.get(1))?.as_str();
+    // A bare host is only the IP when ping was given one; zones like "%12" aren't part of it
+    ip.split('%').next()?.parse::<IpAddr>().ok()?;
+    Some((ip.to_string(), caps.get(3)?.as_str().parse().ok()?))
+}
+
+// Parse everything a reply line says about the echo, if it is a reply at all
+pub fn parse_ping_reply(line: &str) -> Option<ParsedReply> {
+    Some(ParsedReply {
+        latency: parse_ping_line(line)?,
+        ttl: parse_ping_ttl(line),
+        seq: parse_ping_seq(line),
+        bytes: parse_ping_bytes(line),
+        from_ip: parse_ping_from(line),
+    })
+}
+
+// Parse ping output to extract latency
+pub fn parse_ping_line(line: &str) -> Option<Latency> {
+    // Linux/macOS format: "64 bytes from x.x.x.x: icmp_seq=1 ttl=64 time=12.3 ms"
+    // Windows format: "Reply from x.x.x.x: bytes=32 time=12ms TTL=64"
+    // Localized: "Antwort von x.x.x.x: Bytes=32 Zeit=12ms TTL=64"
+
+    let caps = RE_LATENCY.captures(line)?;
+    let time_ms = parse_ms(caps.get(2)?.as_str())?;
+    // Windows prints "time<1ms" for sub-millisecond replies
+    Some(Latency {
+        time_ms,
+        below_one_ms: caps.get(1)?.as_str() == "<",
+    })
+}
+
+// A latency matched where a reply's should be, but out of range; the text
+// as printed, for reporting a corrupted or hostile line
+pub fn parse_latency_anomaly(line: &str) -> Option<String> {
+    let value = RE_LATENCY.captures(line)?.get(2)?.as_str();
+    match parse_ms(value) {
+        Some(_) => None,
+        None => Some(value.to_string()),
+    }
+}
+
+// Latencies beyond this are taken as garbage rather than a slow reply; no
+// ping waits ten minutes for one
+pub const MAX_LATENCY_MS: f64 = 600_000.0;
+
+// Parse a millisecond value, rejecting anything outside 0..=MAX_LATENCY_MS
+fn parse_ms(value: &str) -> Option<f64> {
+    // Some locales print a decimal comma ("Zeit=0,045 ms")
+    let ms: f64 = value.replace(',', ".").parse().ok()?;
+    (0.0..=MAX_LATENCY_MS).contains(&ms).then_some(ms)
+}
+
+// Parse the sequence number from a Unix reply line, "icmp_seq=1" (or "seq=1" on BusyBox)
+pub fn parse_ping_seq(line: &str) -> Option<u32> {
+    let caps = RE_SEQ.captures(line)?;
+    caps.get(1)?.as_str().parse().ok()
+}
+
+// Parse the reply size, "64 bytes from" on Unix and "bytes=32" on Windows
+pub fn parse_ping_bytes(line: &str) -> Option<u32> {
+    let caps = RE_BYTES.captures(line)?;
+    caps.get(1).or_else(|| caps.get(2))?.as_str().parse().ok()
+}
+
+// Parse the replying address, including the "host (ip)" form Unix prints for names
+pub fn parse_ping_from(line: &str) -> Option<IpAddr> {
+    let caps = RE_FROM.captures(line)?;
+    caps.get(1)?.as_str().parse().ok()
+}
+
+// Parse the TTL from a reply line, "ttl=64" on Unix and "TTL=64" on Windows
+pub fn parse_ping_ttl(line: &str) -> Option<u32> {
+    let caps = RE_TTL.captures(line)?;
+    caps.get(1)?.as_str().parse().ok()
+}
+
+// Parse the summary line to extract packet loss percentage
+pub fn parse_packet_loss(line: &str) -> Option<f64> {
+    // Linux/macOS format: "5 packets transmitted, 4 received, 20% packet loss"
+    // Windows format: "Packets: Sent = 4, Received = 3, Lost = 1 (25% loss),"
+
+    let caps = RE_PACKET_LOSS
+        .captures(line)
+        .or_else(|| RE_PACKET_LOSS_LOCALIZED.captures(line))?;
+    caps.get(1)?.as_str().replace(',', ".").parse().ok()
+}
+
+// Parse the round-trip summary printed at the end of a run
+pub fn parse_rtt_summary(line: &str) -> Option<RttSummary> {
+    if let Some(caps) = RE_RTT_UNIX.captures(line) {
+        return Some(RttSummary {
+            min_ms: parse_ms(caps.get(1)?.as_str())?,
+            avg_ms: parse_ms(caps.get(2)?.as_str())?,
+            max_ms: parse_ms(caps.get(3)?.as_str())?,
+            mdev_ms: caps.get(4).and_then(|m| parse_ms(m.as_str())),
+        });
+    }
+
+    // Windows prints min, max, then average
+    let caps = RE_RTT_WINDOWS.captures(line)?;
+    Some(RttSummary {
+        min_ms: parse_ms(caps.get(1)?.as_str())?,
+        avg_ms: parse_ms(caps.get(3)?.as_str())?,
+        max_ms: parse_ms(caps.get(2)?.as_str())?,
+        mdev_ms: None,
+    })
+}
+
+// Work out why an echo failed, if the line reports a failure at all
+pub fn classify_failure(line: &str) -> Option<FailureKind> {
+    let lower = line.to_lowercase();
+    // "Frag needed and DF set" on Unix, "Packet needs to be fragmented but
+    // DF set." on Windows, "message too long" when the local MTU is exceeded
+    if lower.contains("frag needed")
+        || lower.contains("needs to be fragmented")
+        || lower.contains("message too long")
+    {
+        return Some(FailureKind::FragmentationNeeded);
+    }
+    if lower.contains("destination net unreachable")
+        || lower.contains("network is unreachable")
+    {
+        return Some(FailureKind::NetUnreachable);
+    }
+    if lower.contains("destination host unreachable") || lower.contains("no route to host") {
+        return Some(FailureKind::HostUnreachable);
+    }
+    // The closing summary reports on echoes already counted, so it isn't another
+    if is_timeout_line(line) && !is_full_loss_line(line) {
+        return Some(FailureKind::Timeout);
+    }
+    None
+}
+
+// Parse a hop line of traceroute output, either platform's
+pub fn parse_traceroute_hop(line: &str) -> Option<TracerouteHop> {
+    let caps = RE_HOP.captures(line)?;
+    let index = caps.get(1)?.as_str().parse().ok()?;
+    let rest = caps.get(2)?.as_str();
+
+    // Probes answered by different routers list each address, so the
+    // first is taken; "(ip)" and "[ip]" are how names carry theirs
+    let address = rest.split_whitespace().find_map(|token| {
+        token
+            .trim_matches(|c| matches!(c, '(' | ')' | '[' | ']'))
+            .parse()
+            .ok()
+    });
+    let rtt_ms = RE_HOP_RTT
+        .captures_iter(rest)
+        .filter_map(|caps| parse_ms(caps.get(1)?.as_str()))
+        .reduce(f64::min);
+
+    Some(TracerouteHop {
+        index,
+        address,
+        rtt_ms,
+    })
+}
+
+// Recognize an ICMP error from a router: "Time to live exceeded" on Unix,
+// "TTL expired in transit." on Windows, and "Redirect Host" or "Redirect
+// Network" for redirects
+pub fn classify_icmp_error(line: &str) -> Option<IcmpErrorKind> {
+    let lower = line.to_lowercase();
+    if lower.contains("time to live exceeded") || lower.contains("ttl expired in transit") {
+        return Some(IcmpErrorKind::TtlExceeded);
+    }
+    if lower.contains("redirect host") || lower.contains("redirect network") {
+        return Some(IcmpErrorKind::Redirect);
+    }
+    None
+}
+
+// Unix ping marks replies it has already seen with "(DUP!)", a sign of
+// broken multicast or a misbehaving load balancer
+pub fn is_duplicate_line(line: &str) -> bool {
+    line.contains("(DUP!)")
+}
+
+// Check if line indicates a timeout
+pub fn is_timeout_line(line: &str) -> bool {
+    let lower = line.to_lowercase();
+    lower.contains("request timed out")
+        || lower.contains("request timeout")
+        // iputils with `-O`, "no answer yet for icmp_seq=3"
+        || lower.contains("no answer yet")
+        // Localized Windows timeouts
+        || lower.contains("tiempo de espera agotado")
+        || lower.contains("zeitüberschreitung")
+        || lower.contains("délai d'attente")
+        || lower.contains("esgotado o tempo limite")
+        // Fall back on the summary reporting that nothing came back
+        || is_full_loss_line(line)
+}
+
+// Check if line is the summary of a run that got no replies at all
+pub fn is_full_loss_line(line: &str) -> bool {
+    line.to_lowercase().contains("100% packet loss") || RE_FULL_LOSS.is_match(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Output captured from iputils on Linux, macOS, BusyBox and Windows
+    const LINUX_HEADER: &str = "PING google.com (142.250.185.78) 56(84) bytes of data.";
+    const LINUX_HEADER_V6: &str =
+        "PING google.com(lhr25s34-in-x0e.1e100.net (2a00:1450:4009:81f::200e)) 56 data bytes";
+    const LINUX_REPLY: &str =
+        "64 bytes from lhr25s34-in-f14.1e100.net (142.250.185.78): icmp_seq=1 ttl=117 time=12.3 ms";
+    const LINUX_REPLY_V6: &str = "64 bytes from ::1: icmp_seq=2 ttl=64 time=0.045 ms";
+    const LINUX_LOSS: &str = "5 packets transmitted, 4 received, 20% packet loss, time 4006ms";
+    const LINUX_RTT: &str = "rtt min/avg/max/mdev = 12.301/13.020/14.112/0.754 ms";
+    const MACOS_HEADER: &str = "PING google.com (142.250.185.78): 56 data bytes";
+    const MACOS_REPLY: &str = "64 bytes from 142.250.185.78: icmp_seq=0 ttl=117 time=14.123 ms";
+    const MACOS_LOSS: &str = "3 packets transmitted, 3 packets received, 0.0% packet loss";
+    const MACOS_RTT: &str = "round-trip min/avg/max/stddev = 14.123/14.456/14.789/0.272 ms";
+    const BUSYBOX_HEADER: &str = "PING 8.8.8.8 (8.8.8.8): 56 data bytes";
+    const BUSYBOX_REPLY: &str = "64 bytes from 8.8.8.8: seq=0 ttl=117 time=13.512 ms";
+    const BUSYBOX_RTT: &str = "round-trip min/avg/max = 13.512/14.001/14.490 ms";
+    const WINDOWS_HEADER: &str = "Pinging google.com [142.250.185.78] with 32 bytes of data:";
+    const WINDOWS_HEADER_IP: &str = "Pinging 8.8.8.8 with 32 bytes of data:";
+    const WINDOWS_REPLY: &str = "Reply from 8.8.8.8: bytes=32 time=14ms TTL=117";
+    const WINDOWS_LOSS: &str = "    Packets: Sent = 4, Received = 3, Lost = 1 (25% loss),";
+    const WINDOWS_RTT: &str = "    Minimum = 13ms, Maximum = 15ms, Average = 14ms";
+
+    fn ip(text: &str) -> IpAddr {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_headers() {
+        let cases = [
+            (LINUX_HEADER, "142.250.185.78", 56),
+            (LINUX_HEADER_V6, "2a00:1450:4009:81f::200e", 56),
+            ("PING ::1(::1) 56 data bytes", "::1", 56),
+            (MACOS_HEADER, "142.250.185.78", 56),
+            (BUSYBOX_HEADER, "8.8.8.8", 56),
+            (WINDOWS_HEADER, "142.250.185.78", 32),
+            (WINDOWS_HEADER_IP, "8.8.8.8", 32),
+        ];
+        for (line, ip, bytes) in cases {
+            assert_eq!(parse_ping_header(line), Some((ip.to_string(), bytes)), "{line}");
+        }
+        // A bare Windows host is only taken when it is an IP
+        assert_eq!(parse_ping_header("Pinging localhost with 32 bytes of data:"), None);
+        assert_eq!(parse_ping_header(LINUX_REPLY), None);
+    }
+
+    #[test]
+    fn parses_reply_lines() {
+        let cases = [
+            (LINUX_REPLY, 12.3, Some(1), Some(117), 64, "142.250.185.78"),
+            (LINUX_REPLY_V6, 0.045, Some(2), Some(64), 64, "::1"),
+            (MACOS_REPLY, 14.123, Some(0), Some(117), 64, "142.250.185.78"),
+            (BUSYBOX_REPLY, 13.512, Some(0), Some(117), 64, "8.8.8.8"),
+            (WINDOWS_REPLY, 14.0, None, Some(117), 32, "8.8.8.8"),
+        ];
+        for (line, time_ms, seq, ttl, bytes, from) in cases {
+            let reply = parse_ping_reply(line).expect(line);
+            assert_eq!(reply.latency.time_ms, time_ms, "{line}");
+            assert!(!reply.latency.below_one_ms, "{line}");
+            assert_eq!(reply.seq, seq, "{line}");
+            assert_eq!(reply.ttl, ttl, "{line}");
+            assert_eq!(reply.bytes, Some(bytes), "{line}");
+            assert_eq!(reply.from_ip, Some(ip(from)), "{line}");
+
+            assert_eq!(parse_ping_line(line).map(|l| l.time_ms), Some(time_ms), "{line}");
+            assert_eq!(parse_ping_seq(line), seq, "{line}");
+            assert_eq!(parse_ping_ttl(line), ttl, "{line}");
+            assert_eq!(parse_ping_bytes(line), Some(bytes), "{line}");
+            assert_eq!(parse_ping_from(line), Some(ip(from)), "{line}");
+        }
+    }
+
+    #[test]
+    fn parses_ten_thousand_lines() {
+        for seq in 0..10_000u32 {
+            let time_ms = f64::from(seq % 500) + 0.25;
+            let (line, parsed_seq) = match seq % 3 {
+                0 => (
+                    format!("64 bytes from 8.8.8.8: icmp_seq={seq} ttl=117 time={time_ms} ms"),
+                    Some(seq),
+                ),
+                1 => (
+                    format!("64 bytes from 8.8.8.8: seq={seq} ttl=117 time={time_ms} ms"),
+                    Some(seq),
+                ),
+                _ => (format!("Reply from 8.8.8.8: bytes=32 time={time_ms}ms TTL=117"), None),
+            };
+            let reply = parse_ping_reply(&line).expect(&line);
+            assert_eq!(reply.latency.time_ms, time_ms, "{line}");
+            assert_eq!(reply.seq, parsed_seq, "{line}");
+            assert_eq!(reply.ttl, Some(117), "{line}");
+        }
+    }
+
+    #[test]
+    fn flags_sub_millisecond_windows_replies() {
+        let below = parse_ping_reply("Reply from 192.168.1.1: bytes=32 time<1ms TTL=64").unwrap();
+        assert_eq!(below.latency.time_ms, 1.0);
+        assert!(below.latency.below_one_ms);
+        assert_eq!(below.ttl, Some(64));
+        assert_eq!(below.from_ip, Some(ip("192.168.1.1")));
+
+        // A reply that really took 1ms isn't an upper bound
+        let exact = parse_ping_reply("Reply from 192.168.1.1: bytes=32 time=1ms TTL=64").unwrap();
+        assert_eq!(exact.latency.time_ms, 1.0);
+        assert!(!exact.latency.below_one_ms);
+
+        let slow = parse_ping_line(WINDOWS_REPLY).unwrap();
+        assert_eq!(slow.time_ms, 14.0);
+        assert!(!slow.below_one_ms);
+    }
+
+    #[test]
+    fn detects_duplicate_replies() {
+        let line = "64 bytes from 224.0.0.1: icmp_seq=3 ttl=64 time=0.512 ms (DUP!)";
+        assert!(is_duplicate_line(line));
+        assert_eq!(parse_ping_seq(line), Some(3));
+        assert_eq!(parse_ping_reply(line).map(|reply| reply.latency.time_ms), Some(0.512));
+
+        assert!(!is_duplicate_line(LINUX_REPLY));
+        assert!(!is_duplicate_line(WINDOWS_REPLY));
+    }
+
+    #[test]
+    fn ignores_lines_without_a_reply() {
+        let lines = [
+            LINUX_HEADER,
+            LINUX_LOSS,
+            LINUX_RTT,
+            MACOS_HEADER,
+            WINDOWS_HEADER,
+            WINDOWS_RTT,
+            "",
+            "Request timed out.",
+            "Reply from 192.168.1.1: Destination host unreachable.",
+            "--- google.com ping statistics ---",
+            "Ping statistics for 8.8.8.8:",
+            "Approximate round trip times in milli-seconds:",
+        ];
+        for line in lines {
+            assert!(parse_ping_reply(line).is_none(), "{line}");
+            assert!(parse_ping_line(line).is_none(), "{line}");
+        }
+    }
+
+    #[test]
+    fn parses_localized_replies() {
+        let cases = [
+            ("Respuesta desde 8.8.8.8: bytes=32 tiempo=14ms TTL=117", 14.0, false),
+            ("64 bytes desde 8.8.8.8: icmp_seq=1 ttl=117 tiempo=12.3 ms", 12.3, false),
+            ("Antwort von 8.8.8.8: Bytes=32 Zeit=14ms TTL=117", 14.0, false),
+            ("Antwort von 192.168.1.10: Bytes=32 Zeit<1ms TTL=64", 1.0, true),
+            ("64 Bytes von 8.8.8.8: icmp_seq=1 ttl=117 Zeit=0,045 ms", 0.045, false),
+            ("Réponse de 8.8.8.8 : octets=32 temps=14 ms TTL=117", 14.0, false),
+            ("64 octets de 8.8.8.8 : icmp_seq=1 ttl=117 temps=12,3 ms", 12.3, false),
+        ];
+        for (line, time_ms, below_one_ms) in cases {
+            let latency = parse_ping_line(line).expect(line);
+            assert_eq!(latency.time_ms, time_ms, "{line}");
+            assert_eq!(latency.below_one_ms, below_one_ms, "{line}");
+            assert_eq!(parse_ping_ttl(line).map(|ttl| ttl > 0), Some(true), "{line}");
+            assert!(!is_timeout_line(line), "{line}");
+        }
+    }
+
+    #[test]
+    fn detects_localized_timeouts() {
+        let lines = [
+            "Tiempo de espera agotado para esta solicitud.",
+            "Zeitüberschreitung der Anforderung.",
+            "Délai d'attente de la demande dépassé.",
+        ];
+        for line in lines {
+            assert!(is_timeout_line(line), "{line}");
+            assert!(classify_failure(line) == Some(FailureKind::Timeout), "{line}");
+        }
+        // The summaries say every echo timed out, but aren't echoes themselves
+        for line in ["    (100% perdidos),", "    (100% Verlust),", "    (perte 100%),"] {
+            assert!(is_timeout_line(line), "{line}");
+            assert!(classify_failure(line).is_none(), "{line}");
+            assert_eq!(parse_packet_loss(line), Some(100.0), "{line}");
+        }
+        for line in ["    (0% perdidos),", "    (25% Verlust),", "    (perte 0%),"] {
+            assert!(!is_timeout_line(line), "{line}");
+        }
+    }
+
+    #[test]
+    fn parses_localized_rtt_summaries() {
+        let lines = [
+            "    Mínimo = 13ms, Máximo = 15ms, Media = 14ms",
+            "    Minimum = 13ms, Maximum = 15ms, Mittelwert = 14ms",
+            "    Minimum = 13ms, Maximum = 15ms, Moyenne = 14ms",
+        ];
+        for line in lines {
+            let rtt = parse_rtt_summary(line).expect(line);
+            assert_eq!((rtt.min_ms, rtt.avg_ms, rtt.max_ms), (13.0, 14.0, 15.0), "{line}");
+            assert!(parse_ping_line(line).is_none(), "{line}");
+        }
+    }
+
+    #[test]
+    fn flags_out_of_range_latencies() {
+        let line = "64 bytes from 8.8.8.8: icmp_seq=1 ttl=117 time=99999999 ms";
+        assert!(parse_ping_line(line).is_none());
+        assert_eq!(parse_latency_anomaly(line).as_deref(), Some("99999999"));
+        assert_eq!(parse_latency_anomaly(LINUX_REPLY), None);
+        assert_eq!(parse_latency_anomaly(WINDOWS_REPLY), None);
+        assert_eq!(parse_latency_anomaly(LINUX_HEADER), None);
+    }
+
+    #[test]
+    fn rejects_overflowing_numbers() {
+        let line = "64 bytes from 8.8.8.8: icmp_seq=99999999999 ttl=99999999999 \
+                    time=99999999999999999999 ms";
+        assert!(parse_ping_line(line).is_none());
+        assert!(parse_ping_reply(line).is_none());
+        assert_eq!(parse_latency_anomaly(line).as_deref(), Some("99999999999999999999"));
+        assert_eq!(parse_ping_seq(line), None);
+        assert_eq!(parse_ping_ttl(line), None);
+        assert!(parse_rtt_summary("rtt min/avg/max/mdev = 1/2/99999999999999/0.1 ms").is_none());
+    }
+
+    // Feed every parser random bytes, and random text made of the characters
+    // ping's output is made of, making sure nothing panics
+    #[test]
+    fn survives_random_input() {
+        const ALPHABET: &[u8] = b"0123456789.,=<:%()[]/ msbytesfromicmp_seqttlTTL!DUP\t\r";
+        // A fixed-seed LCG keeps any failure reproducible
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move || {
+            state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            (state >> 33) as usize
+        };
+        for round in 0..20_000 {
+            let len = next() % 120;
+            let bytes: Vec<u8> = (0..len)
+                .map(|_| match round % 2 {
+                    0 => next() as u8,
+                    _ => ALPHABET[next() % ALPHABET.len()],
+                })
+                .collect();
+            let line = String::from_utf8_lossy(&bytes);
+            if let Some(latency) = parse_ping_line(&line) {
+                assert!((0.0..=MAX_LATENCY_MS).contains(&latency.time_ms), "{line}");
+            }
+            let _ = parse_ping_header(&line);
+            let _ = parse_ping_reply(&line);
+            let _ = parse_latency_anomaly(&line);
+            let _ = parse_packet_loss(&line);
+            let _ = parse_rtt_summary(&line);
+            let _ = parse_traceroute_hop(&line);
+            let _ = classify_failure(&line);
+            let _ = classify_icmp_error(&line);
+            let _ = is_duplicate_line(&line);
+        }
+    }
+
+    #[test]
+    fn parses_packet_loss() {
+        assert_eq!(parse_packet_loss(LINUX_LOSS), Some(20.0));
+        assert_eq!(parse_packet_loss(MACOS_LOSS), Some(0.0));
+        assert_eq!(
+            parse_packet_loss("3 packets transmitted, 0 packets received, 100% packet loss"),
+            Some(100.0)
+        );
+        assert_eq!(parse_packet_loss(WINDOWS_LOSS), Some(25.0));
+        assert_eq!(parse_packet_loss("    (25% perdidos),"), Some(25.0));
+        assert_eq!(parse_packet_loss("    (perte 12,5%),"), Some(12.5));
+        assert_eq!(parse_packet_loss(LINUX_REPLY), None);
+        assert_eq!(parse_packet_loss(WINDOWS_REPLY), None);
+    }
+
+    #[test]
+    fn parses_rtt_summaries() {
+        let cases = [
+            (LINUX_RTT, 12.301, 13.020, 14.112, Some(0.754)),
+            (MACOS_RTT, 14.123, 14.456, 14.789, Some(0.272)),
+            (BUSYBOX_RTT, 13.512, 14.001, 14.490, None),
+            (WINDOWS_RTT, 13.0, 14.0, 15.0, None),
+        ];
+        for (line, min_ms, avg_ms, max_ms, mdev_ms) in cases {
+            let rtt = parse_rtt_summary(line).expect(line);
+            assert_eq!(rtt.min_ms, min_ms, "{line}");
+            assert_eq!(rtt.avg_ms, avg_ms, "{line}");
+            assert_eq!(rtt.max_ms, max_ms, "{line}");
+            assert_eq!(rtt.mdev_ms, mdev_ms, "{line}");
+        }
+        assert!(parse_rtt_summary(LINUX_REPLY).is_none());
+        assert!(parse_rtt_summary(WINDOWS_LOSS).is_none());
+    }
+
+    #[test]
+    fn classifies_failures() {
+        let cases = [
+            ("Request timed out.", FailureKind::Timeout),
+            ("Request timeout for icmp_seq 0", FailureKind::Timeout),
+            ("no answer yet for icmp_seq=3", FailureKind::Timeout),
+            (
+                "From 192.168.1.1 icmp_seq=1 Destination Host Unreachable",
+                FailureKind::HostUnreachable,
+            ),
+            (
+                "Reply from 192.168.1.1: Destination host unreachable.",
+                FailureKind::HostUnreachable,
+            ),
+            ("ping: sendto: No route to host", FailureKind::HostUnreachable),
+            (
+                "From 10.0.0.1 icmp_seq=2 Destination Net Unreachable",
+                FailureKind::NetUnreachable,
+            ),
+            ("ping: connect: Network is unreachable", FailureKind::NetUnreachable),
+            (
+                "From 192.168.1.1 icmp_seq=1 Frag needed and DF set (mtu = 1400)",
+                FailureKind::FragmentationNeeded,
+            ),
+            ("Packet needs to be fragmented but DF set.", FailureKind::FragmentationNeeded),
+            ("ping: local error: message too long, mtu=1500", FailureKind::FragmentationNeeded),
+        ];
+        for (line, kind) in cases {
+            assert!(classify_failure(line) == Some(kind), "{line}");
+        }
+        let summaries = [
+            "1 packets transmitted, 0 received, 100% packet loss, time 0ms",
+            "    Packets: Sent = 4, Received = 0, Lost = 4 (100% loss),",
+        ];
+        let lines = [LINUX_REPLY, WINDOWS_REPLY, LINUX_LOSS, WINDOWS_HEADER];
+        for line in lines.into_iter().chain(summaries) {
+            assert!(classify_failure(line).is_none(), "{line}");
+        }
+    }
+
+    #[test]
+    fn classifies_icmp_errors() {
+        let cases = [
+            ("From 10.0.0.1 icmp_seq=1 Time to live exceeded", IcmpErrorKind::TtlExceeded),
+            ("Reply from 10.0.0.1: TTL expired in transit.", IcmpErrorKind::TtlExceeded),
+            (
+                "From 192.168.1.1: icmp_seq=1 Redirect Host(New nexthop: 192.168.1.254)",
+                IcmpErrorKind::Redirect,
+            ),
+            (
+                "92 bytes from 192.168.1.1: Redirect Network(New addr: 192.168.1.254)",
+                IcmpErrorKind::Redirect,
+            ),
+        ];
+        for (line, kind) in cases {
+            assert!(classify_icmp_error(line) == Some(kind), "{line}");
+        }
+        assert!(classify_icmp_error(LINUX_REPLY).is_none());
+        assert!(classify_icmp_error("Request timed out.").is_none());
+    }
+
+    #[test]
+    fn parses_traceroute_hops() {
+        let cases = [
+            (" 1  192.168.1.1  0.512 ms  0.430 ms  0.401 ms", 1, Some("192.168.1.1"), Some(0.401)),
+            (" 3  * * *", 3, None, None),
+            (
+                " 5  10.0.0.1  8.1 ms 10.0.0.2  9.2 ms  8.5 ms",
+                5,
+                Some("10.0.0.1"),
+                Some(8.1),
+            ),
+            ("  1    <1 ms    <1 ms    <1 ms  192.168.1.1", 1, Some("192.168.1.1"), Some(1.0)),
+            (
+                "  2    12 ms    11 ms    13 ms  router.example [10.0.0.1]",
+                2,
+                Some("10.0.0.1"),
+                Some(11.0),
+            ),
+            ("  4     *        *        *     Request timed out.", 4, None, None),
+        ];
+        for (line, index, address, rtt_ms) in cases {
+            let hop = parse_traceroute_hop(line).expect(line);
+            assert_eq!(hop.index, index, "{line}");
+            assert_eq!(hop.address, address.map(ip), "{line}");
+            assert_eq!(hop.rtt_ms, rtt_ms, "{line}");
+        }
+        let others = [
+            "traceroute to 8.8.8.8 (8.8.8.8), 30 hops max, 60 byte packets",
+            "Tracing route to 8.8.8.8 over a maximum of 30 hops",
+            "Trace complete.",
+            "",
+        ];
+        for line in others {
+            assert!(parse_traceroute_hop(line).is_none(), "{line}");
+        }
+    }
+}